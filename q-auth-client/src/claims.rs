@@ -0,0 +1,200 @@
+use jsonwebtoken::errors::{Error, ErrorKind};
+use jsonwebtoken::{encode, Header};
+use serde::{Deserialize, Serialize};
+
+use crate::keys::Key;
+
+/// Lifetime of robot tokens when the service does not configure one.
+pub const ROBOT_TOKEN_EXPIRATION: i64 = 60 * 5; // 5 minutes
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimsError {
+    MissingToken,
+    InvalidToken,
+    ExpiredToken,
+    InsufficientRole,
+}
+
+impl From<Error> for ClaimsError {
+    fn from(error: Error) -> Self {
+        match error.kind() {
+            ErrorKind::ExpiredSignature => ClaimsError::ExpiredToken,
+            _ => ClaimsError::InvalidToken,
+        }
+    }
+}
+
+impl std::fmt::Display for ClaimsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClaimsError::MissingToken => write!(f, "no bearer token"),
+            ClaimsError::InvalidToken => write!(f, "invalid token"),
+            ClaimsError::ExpiredToken => write!(f, "expired token"),
+            ClaimsError::InsufficientRole => write!(f, "insufficient role"),
+        }
+    }
+}
+
+impl std::error::Error for ClaimsError {}
+
+/// Roles as stored in the `roles` table of the user service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin = 1,
+    Coordinator = 2,
+    Researcher = 3,
+    Participant = 4,
+    Robot = 5,
+    Guest = 6,
+}
+
+impl Role {
+    pub const ALL: [Role; 6] = [
+        Role::Admin,
+        Role::Coordinator,
+        Role::Researcher,
+        Role::Participant,
+        Role::Robot,
+        Role::Guest,
+    ];
+
+    pub fn id(self) -> i32 {
+        self as i32
+    }
+
+    pub fn from_id(id: i32) -> Option<Role> {
+        Role::ALL.into_iter().find(|role| role.id() == id)
+    }
+
+    /// Name used by the user service.
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Coordinator => "coord",
+            Role::Researcher => "thera",
+            Role::Participant => "user",
+            Role::Robot => "robot",
+            Role::Guest => "guest",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RoleInClaims {
+    pub id: i32,
+    pub name: String,
+}
+
+impl RoleInClaims {
+    pub fn role(&self) -> Option<Role> {
+        Role::from_id(self.id)
+    }
+}
+
+impl From<Role> for RoleInClaims {
+    fn from(role: Role) -> Self {
+        RoleInClaims {
+            id: role.id(),
+            name: role.name().to_string(),
+        }
+    }
+}
+
+/// Project the token was issued for, with the user's membership flags.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ProjectInClaims {
+    pub id: i32,
+    pub active: bool,
+    pub keys: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserInClaims {
+    pub id: i32,
+    pub depends_on: i32,
+    pub role: RoleInClaims,
+    pub user_token: Option<String>,
+    /// Absent in robot tokens and in tokens issued before projects were
+    /// part of the claims.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<ProjectInClaims>,
+    /// Guests only; when the account expires, no token outlives it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+impl Default for UserInClaims {
+    fn default() -> Self {
+        UserInClaims {
+            id: 0,
+            depends_on: 0,
+            role: Role::Guest.into(),
+            user_token: None,
+            project: None,
+            expires_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Claims {
+    pub sub: String,
+    pub user: UserInClaims,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl From<UserInClaims> for Claims {
+    fn from(user: UserInClaims) -> Self {
+        let iat = chrono::Utc::now().timestamp();
+        let exp = iat + ROBOT_TOKEN_EXPIRATION;
+
+        Claims {
+            sub: String::new(),
+            user,
+            iat,
+            exp,
+        }
+    }
+}
+
+impl Claims {
+    /// Restarts the validity window at now, for `lifetime` seconds or until
+    /// the account expires.
+    pub fn expire_in(&mut self, lifetime: i64) -> &mut Self {
+        self.iat = chrono::Utc::now().timestamp();
+        self.exp = match self.user.expires_at {
+            Some(expires_at) => expires_at.min(self.iat + lifetime),
+            None => self.iat + lifetime,
+        };
+        self
+    }
+
+    /// Signs the claims, naming `key` in the `kid` header. Keys built from a
+    /// public key only cannot sign.
+    pub fn sign(&self, key: &Key) -> Result<String, Error> {
+        let encoding = key
+            .encoding()
+            .ok_or_else(|| Error::from(ErrorKind::InvalidKeyFormat))?;
+
+        let mut header = Header::new(key.algorithm());
+        header.kid = Some(key.kid().to_string());
+
+        encode(&header, &self, encoding)
+    }
+}
+
+/// Mints a token other services accept from a robot, i.e. for service to
+/// service calls.
+pub fn robot_token(key: &Key, lifetime: i64) -> Result<String, Error> {
+    let mut claims = Claims::from(UserInClaims {
+        role: Role::Robot.into(),
+        ..UserInClaims::default()
+    });
+
+    claims.expire_in(lifetime).sign(key)
+}
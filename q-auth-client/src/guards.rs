@@ -0,0 +1,97 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::claims::{Claims, ClaimsError, Role};
+use crate::keys::Verifier;
+use crate::token::Token;
+
+struct Stashed(Option<ClaimsError>);
+
+/// Why a claims guard failed on this request, for the service catchers.
+pub fn failure(request: &Request<'_>) -> Option<ClaimsError> {
+    request.local_cache(|| Stashed(None)).0.clone()
+}
+
+pub fn status(error: &ClaimsError) -> Status {
+    match error {
+        ClaimsError::InsufficientRole => Status::Forbidden,
+        _ => Status::Unauthorized,
+    }
+}
+
+fn fail<S>(request: &Request<'_>, error: ClaimsError) -> Outcome<S, ClaimsError> {
+    request.local_cache(|| Stashed(Some(error.clone())));
+
+    Outcome::Error((status(&error), error))
+}
+
+/// Verifies the bearer token and checks its role. Missing tokens fail with
+/// 401 like invalid ones, refresh tokens are not accepted as bearer.
+pub async fn authorize(request: &Request<'_>, roles: &[Role]) -> Outcome<Claims, ClaimsError> {
+    let verifier = match request.rocket().state::<Verifier>() {
+        Some(verifier) => verifier,
+        None => {
+            error!("AUTH: Verifier is not managed");
+            return Outcome::Error((Status::InternalServerError, ClaimsError::InvalidToken));
+        }
+    };
+
+    let token = match Token::from_header(request) {
+        Some(token) => token,
+        None => return fail(request, ClaimsError::MissingToken),
+    };
+
+    let claims = match verifier.verify(&token.0).await {
+        Ok(claims) => claims,
+        Err(error) => return fail(request, error),
+    };
+
+    if claims.user.user_token.is_some() {
+        return fail(request, ClaimsError::InvalidToken);
+    }
+
+    match claims.user.role.role() {
+        Some(role) if roles.contains(&role) => Outcome::Success(claims),
+        _ => fail(request, ClaimsError::InsufficientRole),
+    }
+}
+
+macro_rules! role_guard {
+    ($(#[$doc:meta])* $name:ident, [$($role:ident),+]) => {
+        $(#[$doc])*
+        pub struct $name(pub Claims);
+
+        #[rocket::async_trait]
+        impl<'r> FromRequest<'r> for $name {
+            type Error = ClaimsError;
+
+            async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+                authorize(request, &[$(Role::$role),+]).await.map($name)
+            }
+        }
+    };
+}
+
+role_guard!(
+    /// Any person, robots excluded.
+    AccessClaims,
+    [Admin, Coordinator, Researcher, Participant, Guest]
+);
+role_guard!(AdminClaims, [Admin]);
+role_guard!(
+    /// Staff running studies, admins included.
+    ResearcherClaims,
+    [Admin, Coordinator, Researcher]
+);
+role_guard!(ParticipantClaims, [Participant]);
+role_guard!(GuestClaims, [Guest]);
+role_guard!(
+    /// Service to service calls signed with a robot token.
+    RobotClaims,
+    [Robot]
+);
+role_guard!(
+    /// Admins, or services acting with a robot token.
+    OperatorClaims,
+    [Admin, Robot]
+);
@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::JwkSet;
+use rocket::fairing::AdHoc;
+use rocket::tokio::sync::{Mutex, RwLock};
+use serde::Deserialize;
+
+use crate::keys::{Key, KeySource, Verifier};
+
+/// The `jwks` table of the service configuration, times in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JwksConfig {
+    pub url: String,
+    #[serde(default = "JwksConfig::default_ttl")]
+    pub ttl: u64,
+    #[serde(default = "JwksConfig::default_min_refresh")]
+    pub min_refresh: u64,
+    #[serde(default = "JwksConfig::default_timeout")]
+    pub timeout: u64,
+}
+
+impl JwksConfig {
+    fn default_ttl() -> u64 {
+        300
+    }
+
+    fn default_min_refresh() -> u64 {
+        10
+    }
+
+    fn default_timeout() -> u64 {
+        5
+    }
+}
+
+/// Manages a [`Verifier`] over the JWKS configured in the `jwks` table.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Load jwks", |rocket| async {
+        match rocket.figment().extract_inner::<JwksConfig>("jwks") {
+            Ok(config) => {
+                let cache = JwksCache::new(
+                    config.url,
+                    Duration::from_secs(config.ttl),
+                    Duration::from_secs(config.min_refresh),
+                )
+                .with_timeout(Duration::from_secs(config.timeout));
+                Ok(rocket.manage(Verifier::new(cache)))
+            }
+            Err(e) => {
+                error!("ERROR: config; invalid jwks; {e}");
+                Err(rocket)
+            }
+        }
+    })
+}
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Cached {
+    keys: Vec<Key>,
+    fetched_at: Option<Instant>,
+    /// Last fetch, failed ones included.
+    attempted_at: Option<Instant>,
+}
+
+/// Keys published at the auth service's `/.well-known/jwks.json`, served
+/// when it signs with EdDSA and sets `serve_jwks`. They are refetched once
+/// `ttl` elapses, or on an unknown `kid` (a rotation) but not more often than
+/// `min_refresh`, failed fetches included.
+///
+/// Only one fetch runs at a time and it holds no lock on the keys, so a slow
+/// auth service delays unknown kids but not the tokens already verifiable.
+pub struct JwksCache {
+    url: String,
+    client: reqwest::Client,
+    ttl: Duration,
+    min_refresh: Duration,
+    cache: RwLock<Cached>,
+    fetching: Mutex<()>,
+}
+
+impl JwksCache {
+    pub fn new(url: impl Into<String>, ttl: Duration, min_refresh: Duration) -> Self {
+        JwksCache {
+            url: url.into(),
+            client: JwksCache::client(DEFAULT_TIMEOUT),
+            ttl,
+            min_refresh,
+            cache: RwLock::new(Cached {
+                keys: Vec::new(),
+                fetched_at: None,
+                attempted_at: None,
+            }),
+            fetching: Mutex::new(()),
+        }
+    }
+
+    /// Gives up on a fetch after `timeout`, 5 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = JwksCache::client(timeout);
+        self
+    }
+
+    fn client(timeout: Duration) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default()
+    }
+
+    fn lookup(keys: &[Key], kid: Option<&str>) -> Option<Key> {
+        match kid {
+            Some(kid) => keys.iter().find(|key| key.kid() == kid).cloned(),
+            None => keys.first().cloned(),
+        }
+    }
+
+    async fn fetch(&self) -> Result<Vec<Key>, reqwest::Error> {
+        let set = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+
+        Ok(set.keys.iter().filter_map(Key::from_jwk).collect())
+    }
+}
+
+#[rocket::async_trait]
+impl KeySource for JwksCache {
+    async fn key(&self, kid: Option<&str>) -> Option<Key> {
+        let stale = {
+            let cache = self.cache.read().await;
+            let fresh = cache.fetched_at.is_some_and(|at| at.elapsed() < self.ttl);
+            match JwksCache::lookup(&cache.keys, kid) {
+                Some(key) if fresh => return Some(key),
+                key => key,
+            }
+        };
+
+        // A stale key still verifies while another request fetches
+        let _fetching = match (self.fetching.try_lock(), stale) {
+            (Ok(fetching), _) => fetching,
+            (Err(_), Some(key)) => return Some(key),
+            (Err(_), None) => self.fetching.lock().await,
+        };
+
+        // The fetch this request waited for counts as an attempt too
+        let attempted_at = self.cache.read().await.attempted_at;
+        if attempted_at.is_none_or(|at| at.elapsed() >= self.min_refresh) {
+            let fetched = self.fetch().await;
+
+            let mut cache = self.cache.write().await;
+            cache.attempted_at = Some(Instant::now());
+            match fetched {
+                Ok(keys) => {
+                    cache.keys = keys;
+                    cache.fetched_at = cache.attempted_at;
+                }
+                Err(e) => warn!("AUTH: jwks fetch from {} failed; {e}", self.url),
+            }
+        }
+
+        JwksCache::lookup(&self.cache.read().await.keys, kid)
+    }
+}
@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, EncodingKey, Validation};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::Deserialize;
+
+use crate::claims::{Claims, ClaimsError};
+
+/// Kid of the key derived from `secret_key` and of tokens issued before kids.
+pub const DEFAULT_KID: &str = "default";
+
+const ED25519_SPKI_LEN: usize = 44;
+
+/// Raw entry of `jwt_keys`. `HS256` keys hold a shared secret, `EdDSA` ones
+/// a PKCS#8 private key PEM to sign, or a public key PEM to only verify.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JwtKey {
+    pub kid: String,
+    #[serde(default = "JwtKey::default_alg")]
+    pub alg: String,
+    pub secret: String,
+}
+
+impl JwtKey {
+    fn default_alg() -> String {
+        "HS256".to_string()
+    }
+
+    pub fn hs256(kid: &str, secret: &str) -> Self {
+        JwtKey {
+            kid: kid.to_string(),
+            alg: JwtKey::default_alg(),
+            secret: secret.to_string(),
+        }
+    }
+}
+
+/// Key ready to sign and verify.
+#[derive(Clone)]
+pub struct Key {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    public: Option<Jwk>,
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("signs", &self.encoding.is_some())
+            .finish()
+    }
+}
+
+impl TryFrom<&JwtKey> for Key {
+    type Error = String;
+
+    fn try_from(raw: &JwtKey) -> Result<Self, Self::Error> {
+        if raw.kid.trim().is_empty() || raw.secret.trim().is_empty() {
+            return Err("needs a kid and a secret".to_string());
+        }
+
+        match raw.alg.as_str() {
+            "HS256" => Ok(Key {
+                kid: raw.kid.clone(),
+                algorithm: Algorithm::HS256,
+                encoding: Some(EncodingKey::from_secret(raw.secret.as_ref())),
+                decoding: DecodingKey::from_secret(raw.secret.as_ref()),
+                public: None,
+            }),
+            "EdDSA" => Key::ed25519(&raw.kid, &raw.secret),
+            alg => Err(format!("unsupported alg `{alg}`, use HS256 or EdDSA")),
+        }
+    }
+}
+
+impl Key {
+    fn ed25519(kid: &str, pem: &str) -> Result<Self, String> {
+        let parsed = pem::parse(pem).map_err(|e| format!("invalid pem; {e}"))?;
+
+        let (public, encoding) = match parsed.tag.as_str() {
+            "PRIVATE KEY" => {
+                let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&parsed.contents)
+                    .map_err(|e| format!("invalid Ed25519 private key; {e}"))?;
+                let encoding = EncodingKey::from_ed_der(&parsed.contents);
+
+                (pair.public_key().as_ref().to_vec(), Some(encoding))
+            }
+            "PUBLIC KEY" if parsed.contents.len() == ED25519_SPKI_LEN => {
+                (parsed.contents[ED25519_SPKI_LEN - 32..].to_vec(), None)
+            }
+            tag => return Err(format!("unexpected pem `{tag}`")),
+        };
+
+        let x = URL_SAFE_NO_PAD.encode(public);
+        let decoding = DecodingKey::from_ed_components(&x).map_err(|e| e.to_string())?;
+
+        Ok(Key {
+            kid: kid.to_string(),
+            algorithm: Algorithm::EdDSA,
+            encoding,
+            decoding,
+            public: Some(Jwk {
+                common: CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    algorithm: Some(Algorithm::EdDSA),
+                    key_id: Some(kid.to_string()),
+                    ..CommonParameters::default()
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x,
+                }),
+            }),
+        })
+    }
+
+    /// Verification only key published in a JWKS.
+    pub fn from_jwk(jwk: &Jwk) -> Option<Self> {
+        let algorithm = jwk.common.algorithm.or(match jwk.algorithm {
+            AlgorithmParameters::OctetKeyPair(_) => Some(Algorithm::EdDSA),
+            _ => None,
+        })?;
+
+        Some(Key {
+            kid: jwk.common.key_id.clone()?,
+            algorithm,
+            encoding: None,
+            decoding: DecodingKey::from_jwk(jwk).ok()?,
+            public: Some(jwk.clone()),
+        })
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn encoding(&self) -> Option<&EncodingKey> {
+        self.encoding.as_ref()
+    }
+
+    /// Decodes `token` with this key. Only the key's own algorithm is
+    /// accepted, whatever the token header claims.
+    pub fn verify(&self, token: &str) -> Result<Claims, ClaimsError> {
+        decode::<Claims>(token, &self.decoding, &Validation::new(self.algorithm))
+            .map(|data| data.claims)
+            .map_err(ClaimsError::from)
+    }
+}
+
+/// Keys accepted for verification. The first one signs new tokens, the rest
+/// keep tokens issued before a rotation valid until they expire.
+#[derive(Debug, Clone)]
+pub struct KeyRing(Vec<Key>);
+
+impl KeyRing {
+    pub fn new(keys: &[JwtKey]) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        if keys.is_empty() {
+            errors.push("jwt_keys needs at least one key".to_string());
+        }
+
+        let mut ring = Vec::new();
+        for (i, raw) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|other| other.kid == raw.kid) {
+                errors.push(format!("jwt_keys kid `{}` is repeated", raw.kid));
+            }
+            match Key::try_from(raw) {
+                Ok(key) => ring.push(key),
+                Err(e) => errors.push(format!("jwt_keys[{i}] {e}")),
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(KeyRing(ring)),
+            false => Err(errors),
+        }
+    }
+
+    pub fn signing(&self) -> &Key {
+        &self.0[0]
+    }
+
+    /// Tokens issued before the key ring existed carry no `kid`.
+    pub fn find(&self, kid: Option<&str>) -> Option<&Key> {
+        match kid {
+            Some(kid) => self.0.iter().find(|key| key.kid == kid),
+            None => self
+                .0
+                .iter()
+                .find(|key| key.kid == DEFAULT_KID)
+                .or(self.0.first()),
+        }
+    }
+
+    pub fn kids(&self) -> Vec<String> {
+        self.0.iter().map(|key| key.kid.clone()).collect()
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, ClaimsError> {
+        let header = decode_header(token).map_err(ClaimsError::from)?;
+
+        self.find(header.kid.as_deref())
+            .ok_or(ClaimsError::InvalidToken)?
+            .verify(token)
+    }
+
+    /// Public keys to publish, shared secrets are never part of it.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.0.iter().filter_map(|key| key.public.clone()).collect(),
+        }
+    }
+}
+
+/// Where guards look verification keys up.
+#[rocket::async_trait]
+pub trait KeySource: Send + Sync {
+    async fn key(&self, kid: Option<&str>) -> Option<Key>;
+}
+
+#[rocket::async_trait]
+impl KeySource for KeyRing {
+    async fn key(&self, kid: Option<&str>) -> Option<Key> {
+        self.find(kid).cloned()
+    }
+}
+
+/// Managed state the guards verify bearer tokens with.
+#[derive(Clone)]
+pub struct Verifier(Arc<dyn KeySource>);
+
+impl Verifier {
+    pub fn new(source: impl KeySource + 'static) -> Self {
+        Verifier(Arc::new(source))
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Claims, ClaimsError> {
+        let header = decode_header(token).map_err(ClaimsError::from)?;
+
+        self.0
+            .key(header.kid.as_deref())
+            .await
+            .ok_or(ClaimsError::InvalidToken)?
+            .verify(token)
+    }
+}
@@ -0,0 +1,49 @@
+//! Token verification shared by the q-api services.
+//!
+//! Services verify bearer tokens with the claims guards in [`guards`], which
+//! read a managed [`Verifier`]. With the `fetch` feature it is fed by the keys
+//! q-api-auth publishes ([`jwks::fairing`]), with `push` by the service's own
+//! `jwt_keys` ([`fairing`]).
+
+#[macro_use]
+extern crate rocket;
+
+pub mod claims;
+pub mod guards;
+#[cfg(feature = "fetch")]
+pub mod jwks;
+pub mod keys;
+pub mod token;
+
+#[cfg(test)]
+mod test;
+
+pub use claims::{
+    robot_token, Claims, ClaimsError, ProjectInClaims, Role, RoleInClaims, UserInClaims,
+};
+pub use keys::{JwtKey, Key, KeyRing, KeySource, Verifier};
+
+/// Manages a [`Verifier`] over the `jwt_keys` of the service configuration,
+/// the same table q-api-auth signs with.
+#[cfg(feature = "push")]
+pub fn fairing() -> rocket::fairing::AdHoc {
+    rocket::fairing::AdHoc::try_on_ignite("Load jwt_keys", |rocket| async {
+        let keys = match rocket.figment().extract_inner::<Vec<JwtKey>>("jwt_keys") {
+            Ok(keys) => keys,
+            Err(e) => {
+                error!("ERROR: config; invalid jwt_keys; {e}");
+                return Err(rocket);
+            }
+        };
+
+        match KeyRing::new(&keys) {
+            Ok(ring) => Ok(rocket.manage(Verifier::new(ring))),
+            Err(errors) => {
+                for e in errors {
+                    error!("ERROR: config; {e}");
+                }
+                Err(rocket)
+            }
+        }
+    })
+}
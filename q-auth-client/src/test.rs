@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rocket::tokio::io::{AsyncReadExt, AsyncWriteExt};
+use rocket::tokio::net::TcpListener;
+
+use crate::claims::{robot_token, Claims, ClaimsError, Role, UserInClaims};
+use crate::keys::{JwtKey, Key, KeyRing, KeySource, Verifier};
+
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Private and public PEMs of a fresh Ed25519 key.
+fn ed25519_pems() -> (String, String) {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let spki = [&ED25519_SPKI_PREFIX[..], pair.public_key().as_ref()].concat();
+
+    let private = pem::encode(&pem::Pem {
+        tag: "PRIVATE KEY".to_string(),
+        contents: pkcs8.as_ref().to_vec(),
+    });
+    let public = pem::encode(&pem::Pem {
+        tag: "PUBLIC KEY".to_string(),
+        contents: spki,
+    });
+
+    (private, public)
+}
+
+fn ed25519(kid: &str, pem: &str) -> JwtKey {
+    JwtKey {
+        kid: kid.to_string(),
+        alg: "EdDSA".to_string(),
+        secret: pem.to_string(),
+    }
+}
+
+#[test]
+fn test_sign_and_verify() {
+    let (private, public) = ed25519_pems();
+    let ring =
+        KeyRing::new(&[JwtKey::hs256("old", "secret"), ed25519("ed", &private)]).unwrap();
+
+    let hs = robot_token(ring.signing(), 60).unwrap();
+    let claims = ring.verify(&hs).unwrap();
+    assert_eq!(claims.user.role.role(), Some(Role::Robot));
+
+    let ed = robot_token(ring.find(Some("ed")).unwrap(), 60).unwrap();
+    assert!(ring.verify(&ed).is_ok());
+
+    // A public key verifies but cannot sign
+    let verify_only = KeyRing::new(&[ed25519("ed", &public)]).unwrap();
+    assert!(verify_only.verify(&ed).is_ok());
+    assert!(robot_token(verify_only.signing(), 60).is_err());
+
+    let mut expired = Claims::from(UserInClaims::default());
+    expired.expire_in(-120);
+    let expired = expired.sign(ring.signing()).unwrap();
+    assert_eq!(
+        ring.verify(&expired).unwrap_err(),
+        ClaimsError::ExpiredToken
+    );
+
+    let errors = KeyRing::new(&[JwtKey::hs256("a", "x"), JwtKey::hs256("a", "y")]).unwrap_err();
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_jwks_publishes_public_keys_only() {
+    let (private, _) = ed25519_pems();
+    let ring = KeyRing::new(&[ed25519("ed", &private), JwtKey::hs256("hs", "secret")]).unwrap();
+
+    let jwks = ring.jwks();
+    assert_eq!(jwks.keys.len(), 1);
+
+    let json = rocket::serde::json::to_string(&jwks).unwrap();
+    assert!(json.contains("\"kty\":\"OKP\"") && !json.contains("secret"));
+
+    let published = Key::from_jwk(&jwks.keys[0]).unwrap();
+    let token = robot_token(ring.signing(), 60).unwrap();
+    assert!(published.verify(&token).is_ok());
+}
+
+#[rocket::async_test]
+async fn test_jwks_cache_refetches_on_unknown_kid() {
+    let (first, _) = ed25519_pems();
+    let (second, _) = ed25519_pems();
+    let old = KeyRing::new(&[ed25519("k1", &first)]).unwrap();
+    let rotated = KeyRing::new(&[ed25519("k2", &second), ed25519("k1", &first)]).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!(
+        "http://{}/.well-known/jwks.json",
+        listener.local_addr().unwrap()
+    );
+    let fetches = Arc::new(AtomicUsize::new(0));
+
+    let served = fetches.clone();
+    let bodies =
+        [old.jwks(), rotated.jwks()].map(|jwks| rocket::serde::json::to_string(&jwks).unwrap());
+    rocket::tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = socket.read(&mut buf).await;
+
+            let n = served.fetch_add(1, Ordering::SeqCst);
+            let body = &bodies[n.min(1)];
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\
+                 connection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    let cache = crate::jwks::JwksCache::new(url, Duration::from_secs(300), Duration::ZERO);
+    assert!(cache.key(Some("k1")).await.is_some());
+    assert!(cache.key(Some("k1")).await.is_some());
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    // Signed after a rotation, the new kid triggers a refetch
+    let verifier = Verifier::new(cache);
+    let token = robot_token(rotated.signing(), 60).unwrap();
+    assert!(verifier.verify(&token).await.is_ok());
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+#[rocket::async_test]
+async fn test_jwks_cache_throttles_a_hung_auth_service() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!(
+        "http://{}/.well-known/jwks.json",
+        listener.local_addr().unwrap()
+    );
+    let fetches = Arc::new(AtomicUsize::new(0));
+
+    // Reads the request and never answers
+    let served = fetches.clone();
+    rocket::tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = socket.read(&mut buf).await;
+
+            served.fetch_add(1, Ordering::SeqCst);
+            sockets.push(socket);
+        }
+    });
+
+    let cache =
+        crate::jwks::JwksCache::new(url, Duration::from_secs(300), Duration::from_secs(60))
+            .with_timeout(Duration::from_millis(200));
+    let cache = Arc::new(cache);
+
+    let lookups = (0..4).map(|_| {
+        let cache = cache.clone();
+        rocket::tokio::spawn(async move { cache.key(Some("k1")).await })
+    });
+    for lookup in lookups.collect::<Vec<_>>() {
+        assert!(lookup.await.unwrap().is_none());
+    }
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    // The failed fetch counts for min_refresh
+    let start = std::time::Instant::now();
+    assert!(cache.key(Some("k2")).await.is_none());
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,13 @@
+use rocket::Request;
+
+#[derive(Debug)]
+pub struct Token(pub String);
+
+impl Token {
+    pub fn from_header(request: &Request<'_>) -> Option<Token> {
+        let token = request.headers().get_one("Authorization")?;
+
+        let token = token.replace("Bearer ", "");
+        Some(Token(token))
+    }
+}
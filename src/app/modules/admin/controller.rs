@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::{serde_json, Json};
+use rocket::State;
+use serde::Serialize;
+
+use crate::app::providers::config::{RedactedConfig, SharedConfig};
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::guards::AdminClaims;
+use crate::app::providers::services::audit::{AuditFilter, AuditLog, AuthEvent, EventKind};
+use crate::app::providers::services::guests::sweeper::{self, SweepReport, SweeperConfig};
+use crate::app::providers::services::guests::GuestRegistry;
+use crate::app::providers::services::outbox::{Outbox, OutboxMessage};
+
+const AUDIT_PAGE: i64 = 100;
+const AUDIT_MAX_PAGE: i64 = 10_000;
+const DEAD_LETTERS_PAGE: i64 = 100;
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![config, audit, dead_letters, requeue, sweep_guests]
+}
+
+#[get("/config")]
+pub async fn config(config: &State<SharedConfig>, _admin: AdminClaims) -> Json<RedactedConfig> {
+    Json(config.get().redacted())
+}
+
+/// Query string of `GET /admin/audit`; dates are RFC 3339.
+#[derive(Debug, FromForm)]
+pub struct AuditParams {
+    kind: Option<String>,
+    outcome: Option<String>,
+    actor: Option<i32>,
+    subject: Option<i32>,
+    ip: Option<String>,
+    client: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    cursor: Option<i64>,
+    limit: Option<i64>,
+    format: Option<String>,
+}
+
+impl TryFrom<AuditParams> for AuditFilter {
+    type Error = AuthError;
+
+    fn try_from(params: AuditParams) -> Result<Self, Self::Error> {
+        let date = |name: &str, value: Option<String>| match value {
+            Some(value) => DateTime::parse_from_rfc3339(&value)
+                .map(|date| Some(date.with_timezone(&Utc)))
+                .map_err(|_| {
+                    AuthError::InvalidRequest(format!("{name} must be an RFC 3339 date"))
+                }),
+            None => Ok(None),
+        };
+
+        let kind = match params.kind {
+            Some(kind) => Some(EventKind::parse(&kind).ok_or_else(|| {
+                AuthError::InvalidRequest(format!("unknown event kind `{kind}`"))
+            })?),
+            None => None,
+        };
+
+        Ok(AuditFilter {
+            kind,
+            outcome: params.outcome,
+            actor_id: params.actor,
+            subject_id: params.subject,
+            ip: params.ip,
+            client: params.client,
+            since: date("since", params.since)?,
+            until: date("until", params.until)?,
+            before: params.cursor,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AuditPage {
+    pub events: Vec<AuthEvent>,
+    pub next_cursor: Option<i64>,
+}
+
+/// A page as JSON, or exported as CSV or JSON lines, with the cursor of the
+/// next page also in `X-Next-Cursor`.
+#[derive(Responder)]
+pub struct AuditResponse {
+    body: String,
+    content_type: ContentType,
+    next_cursor: Header<'static>,
+}
+
+#[get("/audit?<params..>")]
+pub async fn audit(
+    log: &State<Arc<dyn AuditLog>>,
+    _admin: AdminClaims,
+    params: AuditParams,
+) -> Result<AuditResponse, AuthError> {
+    let limit = params.limit.unwrap_or(AUDIT_PAGE).clamp(1, AUDIT_MAX_PAGE);
+    let format = params.format.clone().unwrap_or_else(|| "json".to_string());
+    let filter = AuditFilter::try_from(params)?;
+
+    let events = log.query(&filter, limit).await.map_err(|e| {
+        error!("AUDIT: query failed; {e}");
+        AuthError::Http(rocket::http::Status::InternalServerError)
+    })?;
+
+    let next_cursor = match events.len() as i64 == limit {
+        true => events.last().map(|event| event.id),
+        false => None,
+    };
+    let header = Header::new(
+        "X-Next-Cursor",
+        next_cursor
+            .map(|cursor| cursor.to_string())
+            .unwrap_or_default(),
+    );
+    let respond = |body: String, content_type| AuditResponse {
+        body,
+        content_type,
+        next_cursor: header,
+    };
+
+    match format.as_str() {
+        "json" => {
+            let page = serde_json::to_string(&AuditPage {
+                events,
+                next_cursor,
+            })
+            .map_err(|_| AuthError::Http(rocket::http::Status::InternalServerError))?;
+
+            Ok(respond(page, ContentType::JSON))
+        }
+        "csv" => {
+            let rows = events.iter().map(AuthEvent::to_csv);
+            let body = std::iter::once(AuthEvent::CSV_HEADER.to_string())
+                .chain(rows)
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Ok(respond(body + "\n", ContentType::CSV))
+        }
+        "jsonl" => {
+            let body = events
+                .iter()
+                .filter_map(|event| serde_json::to_string(event).ok())
+                .map(|line| line + "\n")
+                .collect();
+
+            Ok(respond(body, ContentType::new("application", "jsonl")))
+        }
+        other => Err(AuthError::InvalidRequest(format!(
+            "format `{other}` is not json, csv or jsonl"
+        ))),
+    }
+}
+
+/// Outbox messages that ran out of attempts, newest first.
+#[get("/outbox/dead?<limit>")]
+pub async fn dead_letters(
+    outbox: &State<Outbox>,
+    _admin: AdminClaims,
+    limit: Option<i64>,
+) -> Result<Json<Vec<OutboxMessage>>, AuthError> {
+    let limit = limit.unwrap_or(DEAD_LETTERS_PAGE).clamp(1, AUDIT_MAX_PAGE);
+
+    outbox
+        .store()
+        .dead_letters(limit)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("OUTBOX: dead letters query failed; {e}");
+            AuthError::Http(Status::InternalServerError)
+        })
+}
+
+/// Gives a dead letter a new round of attempts.
+#[post("/outbox/<id>/requeue")]
+pub async fn requeue(
+    outbox: &State<Outbox>,
+    _admin: AdminClaims,
+    id: i64,
+) -> Result<Status, AuthError> {
+    match outbox.store().requeue(id).await {
+        Ok(true) => Ok(Status::NoContent),
+        Ok(false) => Err(AuthError::Http(Status::NotFound)),
+        Err(e) => {
+            error!("OUTBOX: requeue of {id} failed; {e}");
+            Err(AuthError::Http(Status::InternalServerError))
+        }
+    }
+}
+
+/// Purges the expired guests now, or with `dry_run` only reports them.
+#[post("/guests/sweep?<dry_run>")]
+pub async fn sweep_guests(
+    registry: &State<Arc<dyn GuestRegistry>>,
+    outbox: &State<Outbox>,
+    config: &State<SweeperConfig>,
+    _admin: AdminClaims,
+    dry_run: Option<bool>,
+) -> Result<Json<SweepReport>, AuthError> {
+    let dry_run = dry_run.unwrap_or(false);
+
+    sweeper::sweep(registry.as_ref(), outbox, config, dry_run)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("GUESTS: sweep failed; {e}");
+            AuthError::Http(Status::InternalServerError)
+        })
+}
@@ -1,21 +1,39 @@
+use std::sync::Arc;
+
 use rocket::State;
-use rocket::http::{Cookie, CookieJar, Status};
+use rocket::http::{CookieJar, Status};
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
-use crate::app::providers::guards::claims::RefreshClaims;
-use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::config::{AuthConfig, ClientType, SharedConfig};
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::guards::audit::Audit;
+use crate::app::providers::guards::claims::{RefreshClaims, RobotClaims};
+use crate::app::providers::guards::client::RegisteredClient;
+use crate::app::providers::guards::csrf::CsrfChecked;
+use crate::app::providers::guards::idempotency::IdempotencyKey;
+use crate::app::providers::services::audit::EventKind;
 use crate::app::providers::services::claims::UserInClaims;
+use crate::app::providers::services::clients::{ProfileClient, UserClient};
+use crate::app::providers::services::outbox::Effect;
+use crate::app::providers::services::revocation::{Revocation, Revocations};
+use crate::app::providers::services::token::Token;
 
 use crate::app::modules::auth::services::helpers;
+use crate::app::modules::auth::services::onboarding::Onboarding;
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![options_all, auth_bypass, auth, login, logout]
-}
-
-#[options("/<_..>")]
-pub async fn options_all() -> Status {
-    Status::Ok
+    routes![
+        auth_bypass,
+        auth,
+        login,
+        refresh,
+        project,
+        project_native,
+        logout,
+        logout_native,
+        revoke
+    ]
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,120 +41,369 @@ pub async fn options_all() -> Status {
 pub struct AuthUser {
     pub user: UserInClaims,
     pub access_token: String,
+    /// Only sent to native clients, browsers get the cookie instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RefreshRequest {
+    pub refresh_token: String,
 }
 
 // WARNING: This is only for testing purposes
 #[get("/bypass/<id>")]
-pub async fn auth_bypass(fetch: &State<Fetch>, cookie: &CookieJar<'_>, id: i32) -> Result<Json<AuthUser>, Status> {
-    let user_in_claims = helpers::user_request(fetch, id).await;
-    if let Err(_) = user_in_claims {
-        return Err(Status::InternalServerError);
+pub async fn auth_bypass(
+    config: &State<SharedConfig>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    audit: Audit,
+    id: i32,
+) -> Result<Json<AuthUser>, AuthError> {
+    let result = async {
+        let user_in_claims = helpers::user_request(users.as_ref(), id).await?;
+
+        issue(&config.get(), cookie, ClientType::Browser, user_in_claims).await
     }
-    let user_in_claims = user_in_claims.unwrap();
+    .await;
+
+    audit.track(EventKind::Bypass, None, Some(id), result).await
+}
+
+#[get("/")]
+pub async fn auth(
+    config: &State<SharedConfig>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    audit: Audit,
+    _csrf: CsrfChecked,
+    claims: Result<RefreshClaims, AuthError>,
+) -> Result<Json<AuthUser>, AuthError> {
+    let claims = audit.rejected(EventKind::Refresh, claims).await?;
 
-    let tokens = helpers::token_generator(user_in_claims.clone()).await;
+    let user = claims.0.user;
+    let result = async {
+        let user_in_claims =
+            helpers::refresh_user(users.as_ref(), &user, user.project.as_ref().map(|p| p.id))
+                .await?;
 
-    if let Err(_) = tokens {
-        return Err(Status::NotFound);
+        issue(&config.get(), cookie, ClientType::Browser, user_in_claims).await
     }
-    let (access_token, refresh_token) = tokens.unwrap();
+    .await;
 
-    cookie.add_private(Cookie::new("refresh_token", refresh_token));
+    audit
+        .track(EventKind::Refresh, Some(user.id), Some(user.id), result)
+        .await
+}
 
-    let auth_user = AuthUser {
-        user: user_in_claims,
-        access_token,
+#[allow(clippy::too_many_arguments)]
+#[post("/login", data = "<token>")]
+pub async fn login(
+    config: &State<SharedConfig>,
+    profile: &State<Arc<dyn ProfileClient>>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    onboarding: Onboarding,
+    audit: Audit,
+    client: Result<RegisteredClient, AuthError>,
+    idempotency: IdempotencyKey,
+    token: Json<String>,
+) -> Result<Json<AuthUser>, AuthError> {
+    let config = config.get();
+    let token = token.into_inner();
+    let kind = match helpers::guest_project(&token) {
+        Ok(Some(_)) => EventKind::GuestCreated,
+        _ => EventKind::Login,
     };
-    Ok(Json(auth_user))
+    let client = audit.rejected(kind, client).await?;
+
+    // A retried guest login must not create a second guest. Only the user
+    // is remembered, a replay gets tokens of its own.
+    let result = idempotency
+        .once(token.as_bytes(), async {
+            helpers::login_request(profile.as_ref(), users.as_ref(), &onboarding, token.clone())
+                .await
+        })
+        .await;
+    let kind = match &result {
+        Ok(once) if once.replayed() => EventKind::Login,
+        _ => kind,
+    };
+    let result = match result {
+        Ok(once) => issue(&config, cookie, client.kind, once.into_inner()).await,
+        Err(e) => Err(e),
+    };
+
+    let user_id = result.as_ref().ok().map(|auth_user| auth_user.user.id);
+    audit.track(kind, user_id, user_id, result).await
 }
 
-#[get("/")]
-pub async fn auth(fetch: &State<Fetch>, cookie: &CookieJar<'_>, claims: RefreshClaims) -> Result<Json<AuthUser>, Status> {
-    let user_in_claims = helpers::user_request(fetch, claims.0.user.id).await;
-    if let Err(_) = user_in_claims {
-        return Err(Status::InternalServerError);
+#[post("/refresh", data = "<body>")]
+pub async fn refresh(
+    config: &State<SharedConfig>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    revocations: &State<Revocations>,
+    audit: Audit,
+    client: Result<RegisteredClient, AuthError>,
+    body: Json<RefreshRequest>,
+) -> Result<Json<AuthUser>, AuthError> {
+    let config = config.get();
+    let claims = match client {
+        Ok(client) => native_claims(&config, revocations, &client, body.into_inner())
+            .await
+            .map(|claims| (client, claims)),
+        Err(e) => Err(e),
+    };
+    let (client, claims) = audit.rejected(EventKind::Refresh, claims).await?;
+
+    let user = claims.0.user;
+    let result = async {
+        let user_in_claims =
+            helpers::refresh_user(users.as_ref(), &user, user.project.as_ref().map(|p| p.id))
+                .await?;
+
+        issue(&config, cookie, client.kind, user_in_claims).await
     }
-    let user_in_claims = user_in_claims.unwrap();
+    .await;
 
-    match helpers::token_generator(user_in_claims.clone()).await {
-        Ok((refresh_token, access_token)) => {
-            cookie.add_private(Cookie::new("refresh_token", refresh_token));
+    audit
+        .track(EventKind::Refresh, Some(user.id), Some(user.id), result)
+        .await
+}
 
-            let auth_user = AuthUser {
-                user: user_in_claims,
-                access_token,
-            };
+/// Reissues the tokens for another project of the user. Browsers send the
+/// refresh cookie, native clients the refresh token in a JSON body.
+#[post("/project/<project_id>", rank = 2)]
+pub async fn project(
+    config: &State<SharedConfig>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    audit: Audit,
+    _csrf: CsrfChecked,
+    claims: Result<RefreshClaims, AuthError>,
+    project_id: i32,
+) -> Result<Json<AuthUser>, AuthError> {
+    let claims = audit.rejected(EventKind::ProjectSwitch, claims).await?;
 
-            Ok(Json(auth_user))
-        }
-        Err(e) => {
-            return Err(e);
-        }
+    let user_id = claims.0.user.id;
+    let result = async {
+        let user_in_claims =
+            helpers::refresh_user(users.as_ref(), &claims.0.user, Some(project_id)).await?;
+
+        issue(&config.get(), cookie, ClientType::Browser, user_in_claims).await
     }
+    .await;
+
+    audit
+        .track(
+            EventKind::ProjectSwitch,
+            Some(user_id),
+            Some(user_id),
+            result,
+        )
+        .await
 }
 
-#[post("/login", data = "<token>")]
-pub async fn login(fetch: &State<Fetch>, cookie: &CookieJar<'_>, token: Json<String>) -> Result<Json<AuthUser>, Status> {
-    // Request the user_id from the profile api
-    let token = token.into_inner();
+#[allow(clippy::too_many_arguments)]
+#[post("/project/<project_id>", format = "json", data = "<body>")]
+pub async fn project_native(
+    config: &State<SharedConfig>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    revocations: &State<Revocations>,
+    audit: Audit,
+    client: Result<RegisteredClient, AuthError>,
+    body: Json<RefreshRequest>,
+    project_id: i32,
+) -> Result<Json<AuthUser>, AuthError> {
+    let config = config.get();
+    let claims = match client {
+        Ok(client) => native_claims(&config, revocations, &client, body.into_inner())
+            .await
+            .map(|claims| (client, claims)),
+        Err(e) => Err(e),
+    };
+    let (client, claims) = audit.rejected(EventKind::ProjectSwitch, claims).await?;
 
-    let mut guest = false;
-    let project_id: i32;
+    let user_id = claims.0.user.id;
+    let result = async {
+        let user_in_claims =
+            helpers::refresh_user(users.as_ref(), &claims.0.user, Some(project_id)).await?;
 
-    if token.contains("guest") && token.contains(".") {
-        guest = true;
-        let parts = token.split(".").collect::<Vec<&str>>();
-        project_id = parts[1].parse::<i32>().unwrap();
-    } else {
-        project_id = 0;
+        issue(&config, cookie, client.kind, user_in_claims).await
     }
+    .await;
 
-    let user_in_claims = if guest {
-        match helpers::create_guest(fetch, project_id).await {
-            Ok(user_in) => user_in,
-            _ => return Err(Status::InternalServerError)
-        }
-    } else {
-        let user_id = match helpers::profile_request(fetch, token).await {
-            Ok(id) => id,
-            _ => return Err(Status::Unauthorized)
-        };
-
-        match helpers::user_request(fetch, user_id).await {
-            Ok(user) => user,
-            _ => return Err(Status::InternalServerError)
+    audit
+        .track(
+            EventKind::ProjectSwitch,
+            Some(user_id),
+            Some(user_id),
+            result,
+        )
+        .await
+}
+
+#[get("/logout")]
+pub async fn logout(
+    config: &State<SharedConfig>,
+    cookie: &CookieJar<'_>,
+    audit: Audit,
+    _csrf: CsrfChecked,
+    claims: Result<RefreshClaims, AuthError>,
+) -> Result<Status, AuthError> {
+    let claims = audit.rejected(EventKind::Logout, claims).await?;
+
+    let user_id = claims.0.user.id;
+    config.get().cookie.clear(cookie);
+
+    let effects = vec![Effect::ResetTokens { user_id }];
+    audit
+        .record_with(
+            EventKind::Logout,
+            Some(user_id),
+            Some(user_id),
+            Ok(()),
+            effects,
+        )
+        .await;
+
+    Ok(Status::Ok)
+}
+
+#[post("/logout", data = "<body>")]
+pub async fn logout_native(
+    config: &State<SharedConfig>,
+    revocations: &State<Revocations>,
+    audit: Audit,
+    client: Result<RegisteredClient, AuthError>,
+    body: Json<RefreshRequest>,
+) -> Result<Status, AuthError> {
+    let claims = match client {
+        Ok(client) => {
+            native_claims(&config.get(), revocations, &client, body.into_inner()).await
         }
+        Err(e) => Err(e),
     };
+    let claims = audit.rejected(EventKind::Logout, claims).await?;
 
-    match helpers::token_generator(user_in_claims.clone()).await {
-        Ok((refresh_token, access_token)) => {
-            cookie.add_private(Cookie::new("refresh_token", refresh_token.clone()));
+    let user_id = claims.0.user.id;
+    let effects = vec![Effect::ResetTokens { user_id }];
+    audit
+        .record_with(
+            EventKind::Logout,
+            Some(user_id),
+            Some(user_id),
+            Ok(()),
+            effects,
+        )
+        .await;
 
-            let auth_user = AuthUser {
-                user: user_in_claims,
-                access_token,
-            };
+    Ok(Status::Ok)
+}
 
-            Ok(Json(auth_user))
-        }
-        Err(e) => {
-            return Err(e);
-        }
+/// Called by the user service when it deactivates a user or a membership,
+/// so the refresh tokens issued so far stop working right away.
+#[post("/revoke", data = "<body>")]
+pub async fn revoke(
+    config: &State<SharedConfig>,
+    revocations: &State<Revocations>,
+    audit: Audit,
+    _robot: RobotClaims,
+    body: Json<Revocation>,
+) -> Status {
+    let revocation = body.into_inner();
+
+    // The user service retries until the revocation is stored
+    let result = revocations
+        .revoke(&revocation, config.get().refresh_token_expiration)
+        .await
+        .map_err(|e| {
+            error!("AUTH: revocation of {revocation:?} not stored; {e}");
+            AuthError::Http(Status::ServiceUnavailable)
+        });
+    if result.is_ok() {
+        info!("AUTH: revoked tokens of {revocation:?}");
+    }
+
+    audit
+        .record(
+            EventKind::Revocation,
+            None,
+            Some(revocation.user_id),
+            result.as_ref().map(|_| ()),
+        )
+        .await;
+
+    match result {
+        Ok(()) => Status::NoContent,
+        Err(e) => e.status(),
     }
 }
 
-#[get("/logout")]
-pub async fn logout(fetch: &State<Fetch>, cookie: &CookieJar<'_>, claims: RefreshClaims) -> Status {
-    if let Err(_) = helpers::delete_token(fetch, claims.0.user.id).await {
-        println!("AUTH: logout: fcm_token_delete failed");
-    };
+/// Validates a refresh token sent in the body, only native clients may.
+async fn native_claims(
+    config: &AuthConfig,
+    revocations: &Revocations,
+    client: &RegisteredClient,
+    body: RefreshRequest,
+) -> Result<RefreshClaims, AuthError> {
+    if client.kind != ClientType::Native {
+        warn!(
+            "AUTH: client `{}` may not refresh from the body",
+            client.id.as_deref().unwrap_or("browser")
+        );
+        return Err(AuthError::UnauthorizedClient);
+    }
 
-    match cookie.get_private("refresh_token") {
-        Some(c) => {
-            cookie.remove_private(c);
-        },
-        None => { }
+    RefreshClaims::from_token(config, revocations, Token(body.refresh_token)).await
+}
+
+/// Rotates the tokens and hands the refresh one out the way the client
+/// type expects it.
+async fn issue(
+    config: &AuthConfig,
+    cookie: &CookieJar<'_>,
+    kind: ClientType,
+    user_in_claims: UserInClaims,
+) -> Result<Json<AuthUser>, AuthError> {
+    let auth_user = tokens(config, user_in_claims).await?;
+
+    Ok(deliver(config, cookie, kind, auth_user))
+}
+
+/// Both tokens in the body, before `deliver` decides where the refresh
+/// token goes.
+async fn tokens(
+    config: &AuthConfig,
+    user_in_claims: UserInClaims,
+) -> Result<AuthUser, AuthError> {
+    let (refresh_token, access_token) =
+        helpers::token_generator(config, user_in_claims.clone()).await?;
+
+    Ok(AuthUser {
+        user: user_in_claims,
+        access_token,
+        refresh_token: Some(refresh_token),
+    })
+}
+
+/// Browsers get the refresh token in the cookie, native clients in the body.
+fn deliver(
+    config: &AuthConfig,
+    cookie: &CookieJar<'_>,
+    kind: ClientType,
+    mut auth_user: AuthUser,
+) -> Json<AuthUser> {
+    if kind == ClientType::Browser {
+        if let Some(refresh_token) = auth_user.refresh_token.take() {
+            config
+                .cookie
+                .set(cookie, refresh_token, config.refresh_token_expiration);
+        }
     }
 
-    Status::Ok
+    Json(auth_user)
 }
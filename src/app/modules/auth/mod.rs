@@ -1,2 +1,2 @@
 pub mod controller;
-mod services;
+pub mod services;
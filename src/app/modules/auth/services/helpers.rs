@@ -1,230 +1,148 @@
-use rocket::State;
-use rocket::http::Status;
-use serde::{Serialize, Deserialize};
-
-use crate::app::providers::config_getter::ConfigGetter;
-use crate::app::providers::models::message::PubNewToken;
-use crate::app::providers::models::user::{PubNewUser, PubUserExpanded};
-use crate::app::providers::services::claims::{Claims, UserInClaims};
-use crate::app::providers::services::fetch::Fetch;
-
-pub async fn create_guest(fetch: &State<Fetch>, project_id: i32) -> Result<UserInClaims, Status> {
-    let robot_token = match Fetch::robot_token().await {
-        Ok(token) => token,
-        Err(_) => return Err(Status::InternalServerError),
-    };
-
-    let user_api_url = ConfigGetter::get_entity_url("user")
-        .unwrap_or("http://localhost:8002/api/v1/user/".to_string());
-
-    let new_user = PubNewUser {
-        depends_on: 1,
-        role_id: 4, // should be 6 to match guest
-        active: Some(true),
-        project_id,
-    };
-
-    let res;
-    {
-        let client = fetch.client.lock().await;
-        res = client
-            .post(&user_api_url)
-            .header("Accept", "application/json")
-            .header("Authorization", robot_token)
-            .header("Content-Type", "application/json")
-            .json(&new_user)
-            .send()
-            .await;
+use crate::app::providers::config::AuthConfig;
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::claims::{
+    Claims, EncodeClaims, ProjectInClaims, UserInClaims,
+};
+use crate::app::providers::services::clients::{ProfileClient, UserClient};
+use crate::app::providers::services::upstream::UpstreamErrorKind;
+
+use super::onboarding::Onboarding;
+
+/// Resolves the user behind a login token, onboarding it for guest tokens.
+pub async fn login_request(
+    profile: &dyn ProfileClient,
+    users: &dyn UserClient,
+    onboarding: &Onboarding,
+    token: String,
+) -> Result<UserInClaims, AuthError> {
+    match guest_project(&token)? {
+        Some(project_id) => onboarding.guest(project_id).await,
+        None => {
+            // Request the user_id from the profile api
+            let user_id = profile_request(profile, token).await?;
+            project_user(users, user_id, None).await
+        }
     }
+}
 
-    match res {
-        Ok(res) => {
-            if res.status() != 200 {
-                return Err(Status::from_code(res.status().as_u16()).unwrap());
-            }
-
-            let user_exp = match res.json::<PubUserExpanded>().await {
-                Ok(user_exp) => user_exp,
-                Err(e) => {
-                    println!("Error: {}; trying to get user_exp", e);
-                    return Err(Status::InternalServerError)
-                },
-            };
-
-            Ok(user_exp.into())
+pub async fn profile_request(
+    profile: &dyn ProfileClient,
+    token: String,
+) -> Result<i32, AuthError> {
+    match profile.verify_token(&token).await {
+        Ok(user_id) => Ok(user_id),
+        Err(e) if matches!(e.kind, UpstreamErrorKind::Status(400 | 401 | 403 | 404)) => {
+            Err(AuthError::InvalidCredentials)
         }
-        Err(_) => return Err(Status::InternalServerError),
+        Err(e) => Err(e.into()),
     }
-
 }
 
-pub async fn delete_token(fetch: &State<Fetch>, user_id: i32) -> Result<Status, Status> {
-    let robot_token = match Fetch::robot_token().await {
-        Ok(token) => token,
-        Err(_) => return Err(Status::InternalServerError),
-    };
-
-    let message_url = ConfigGetter::get_entity_url("message")
-        .unwrap_or("http://localhost:8005/api/v1/messaging/".to_string())
-        + "token/user/"
-        + user_id.to_string().as_str();
-
-    let res;
-    {
-        let client = fetch.client.lock().await;
-        res = client
-            .put(message_url)
-            .header("Accept", "application/json")
-            .header("Authorization", robot_token)
-            .header("Content-Type", "application/json")
-            .json(& PubNewToken { user_id, fcm_token: None, web_token: None })
-            .send()
-            .await;
+/// The user service answers 410 Gone for deactivated users.
+pub async fn user_request(
+    users: &dyn UserClient,
+    user_id: i32,
+) -> Result<UserInClaims, AuthError> {
+    match users.user_in_claims(user_id).await {
+        Ok(user) => Ok(user),
+        Err(e) if e.kind == UpstreamErrorKind::Status(404) => Err(AuthError::UserNotFound),
+        Err(e) if e.kind == UpstreamErrorKind::Status(410) => Err(AuthError::Inactive),
+        Err(e) => Err(e.into()),
     }
+}
 
-    match res {
-        Ok(res) => {
-            if res.status() != 200 {
-                println!("Error: {}; trying to delete token from message", res.status().as_str());
-                return Err(Status::from_code(res.status().as_u16()).unwrap());
-            }
-
-            Ok(Status::Ok)
+/// The user as an active member of `project_id`, or of its default project
+/// for `None`. Refreshes pass the project of the old token so a switch
+/// sticks, and so a deactivation is noticed within an access token lifetime.
+pub async fn project_user(
+    users: &dyn UserClient,
+    user_id: i32,
+    project_id: Option<i32>,
+) -> Result<UserInClaims, AuthError> {
+    let mut user = user_request(users, user_id).await?;
+
+    if let Some(project_id) = project_id {
+        if user.project.as_ref().map(|project| project.id) != Some(project_id) {
+            user.project = Some(membership(users, user_id, project_id).await?);
         }
-        Err(e) => {
-            println!("Error: {};trying to delete token from message", e);
-            return Err(Status::InternalServerError)},
     }
-}
 
-// pub async fn fcm_token_delete(fetch: &State<Fetch>, user_id: i32) -> Result<(), Status> {
-//     #[derive(Serialize, Deserialize)]
-//     struct NewFcmToken {
-//         pub user_id: i32,
-//         pub token: Option<String>,
-//     }
-
-//     let robot_token = match Fetch::robot_token().await {
-//         Ok(token) => token,
-//         Err(_) => return Err(Status::InternalServerError),
-//     };
-
-//     let fcm_api_url = ConfigGetter::get_entity_url("fcm")
-//         .unwrap_or("http://localhost:8005/api/v1/fcm/".to_string())
-//         + "token/"
-//         + user_id.to_string().as_str()
-//         + "/user";
-
-//     let res;
-//     {
-//         let client = fetch.client.lock().await;
-//         res = client
-//             .put(&fcm_api_url)
-//             .header("Accept", "application/json")
-//             .header("Authorization", robot_token)
-//             .header("Content-Type", "application/json")
-//             .json(&NewFcmToken {
-//                 user_id,
-//                 token: None,
-//             })
-//             .send()
-//             .await;
-//     }
-
-//     match res {
-//         Ok(_) => Ok(()),
-//         Err(_) => Err(Status::InternalServerError),
-//     }
-// }
-
-pub async fn profile_request(fetch: &State<Fetch>, token: String) -> Result<i32, Status> {
-    let robot_token = match Fetch::robot_token().await {
-        Ok(token) => token,
-        Err(_) => return Err(Status::InternalServerError),
-    };
+    if user.project.as_ref().is_some_and(|project| !project.active) {
+        info!("AUTH: user {user_id} has an inactive membership");
+        return Err(AuthError::Inactive);
+    }
 
-    let profile_api_url = ConfigGetter::get_entity_url("profile")
-        .unwrap_or("http://localhost:8001/api/v1/profile/".to_string())
-        + "token";
+    Ok(user)
+}
 
-    let res;
+/// `project_user` when refreshing the tokens of `previous`. Guests keep the
+/// expiry of their first token and get nothing once it has passed.
+pub async fn refresh_user(
+    users: &dyn UserClient,
+    previous: &UserInClaims,
+    project_id: Option<i32>,
+) -> Result<UserInClaims, AuthError> {
+    if previous
+        .expires_at
+        .is_some_and(|at| at <= chrono::Utc::now().timestamp())
     {
-        let client = fetch.client.lock().await;
-        res = client
-            .post(&profile_api_url)
-            .header("Accept", "application/json")
-            .header("Authorization", robot_token)
-            .header("Content-Type", "application/json")
-            .json(&token)
-            .send()
-            .await;
+        info!("AUTH: guest {} has expired", previous.id);
+        return Err(AuthError::GuestExpired);
     }
 
-    match res {
-        Ok(res) => {
-            if res.status() != 200 {
-                return Err(Status::from_code(res.status().as_u16()).unwrap());
-            }
+    let mut user = project_user(users, previous.id, project_id).await?;
+    user.expires_at = previous.expires_at;
 
-            Ok(res.json::<i32>().await.unwrap())
-        }
-        Err(_) => return Err(Status::InternalServerError),
-    }
+    Ok(user)
 }
 
-pub async fn user_request(fetch: &State<Fetch>, user_id: i32) -> Result<UserInClaims, Status> {
-    // Prepare the robot token
-    let robot_token = match Fetch::robot_token().await {
-        Ok(token) => token,
-        Err(_) => return Err(Status::InternalServerError),
+pub async fn membership(
+    users: &dyn UserClient,
+    user_id: i32,
+    project_id: i32,
+) -> Result<ProjectInClaims, AuthError> {
+    let memberships = match users.memberships(user_id).await {
+        Ok(memberships) => memberships,
+        Err(e) if e.kind == UpstreamErrorKind::Status(404) => {
+            return Err(AuthError::UserNotFound)
+        }
+        Err(e) => return Err(e.into()),
     };
 
-    // Prepare the url
-    let user_url = ConfigGetter::get_entity_url("user")
-        .unwrap_or("http://localhost:8002/api/v1/user/".to_string())
-        + user_id.to_string().as_str()
-        + "/userinclaims";
-
-    // Make the request
-    let res;
-    {
-        let client = fetch.client.lock().await;
-        res = client
-            .get(&user_url)
-            .header("Accept", "application/json")
-            .header("Authorization", robot_token)
-            .header("Content-Type", "application/json")
-            .send()
-            .await;
-    }
-
-    match res {
-        Ok(res) => {
-            if res.status() != 200 {
-                return Err(Status::from_code(res.status().as_u16()).unwrap());
-            }
-
-            Ok(res.json::<UserInClaims>().await.unwrap())
-        }
-        Err(_) => return Err(Status::InternalServerError),
-    }
+    memberships
+        .into_iter()
+        .find(|membership| membership.project_id == project_id)
+        .map(ProjectInClaims::from)
+        .ok_or(AuthError::NotAMember)
 }
 
-pub async fn token_generator(user_in_claims: UserInClaims) -> Result<(String, String), Status> {
+pub async fn token_generator(
+    config: &AuthConfig,
+    user_in_claims: UserInClaims,
+) -> Result<(String, String), AuthError> {
     let mut claims: Claims = Claims::from(user_in_claims);
 
-    let refresh_token = claims.encode_for_refresh();
-    if let Err(_) = refresh_token {
-        return Err(Status::InternalServerError);
-    }
-    let refresh_token = refresh_token.unwrap();
+    let refresh_token = claims.encode_for_refresh(config).map_err(|e| {
+        error!("AUTH: refresh token could not be encoded; {e}");
+        AuthError::TokenEncoding
+    })?;
 
     // encode_for_access removes claims.user.user_token
-    let access_token = claims.encode_for_access();
-    if let Err(_) = access_token {
-        return Err(Status::InternalServerError);
-    }
-    let access_token = access_token.unwrap();
+    let access_token = claims.encode_for_access(config).map_err(|e| {
+        error!("AUTH: access token could not be encoded; {e}");
+        AuthError::TokenEncoding
+    })?;
 
     Ok((refresh_token, access_token))
 }
+
+/// Parses the `guest.<project_id>` login token.
+pub fn guest_project(token: &str) -> Result<Option<i32>, AuthError> {
+    let Some(project_id) = token.strip_prefix("guest.") else {
+        return Ok(None);
+    };
+
+    project_id.parse::<i32>().map(Some).map_err(|_| {
+        AuthError::InvalidRequest("Guest tokens must look like guest.<project_id>".to_string())
+    })
+}
@@ -0,0 +1,238 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::json;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::models::user::PubNewUser;
+use crate::app::providers::services::claims::{Role, UserInClaims};
+use crate::app::providers::services::clients::{MessagingClient, ProjectClient, UserClient};
+use crate::app::providers::services::guests::{Guest, GuestRegistry};
+use crate::app::providers::services::outbox::{Effect, Outbox};
+use crate::app::providers::services::upstream::{UpstreamError, UpstreamErrorKind};
+
+const ATTEMPTS: usize = 3;
+
+/// Steps that went through, undone in reverse order when a later one fails.
+#[derive(Debug)]
+enum Done {
+    User(i32),
+    Membership { project_id: i32, user_id: i32 },
+    MessagingToken(i32),
+}
+
+impl Done {
+    fn undo(&self) -> Effect {
+        match *self {
+            Done::User(user_id) => Effect::DeleteUser { user_id },
+            Done::Membership {
+                project_id,
+                user_id,
+            } => Effect::RemoveMembership {
+                project_id,
+                user_id,
+            },
+            Done::MessagingToken(user_id) => Effect::DeleteMessagingToken { user_id },
+        }
+    }
+}
+
+/// Creates a guest in every service on its first login: the user, its
+/// project membership and its messaging token row. The guest is registered
+/// last, with the lifetime of its project, so the sweeper can purge it.
+pub struct Onboarding {
+    users: Arc<dyn UserClient>,
+    projects: Arc<dyn ProjectClient>,
+    messaging: Arc<dyn MessagingClient>,
+    guests: Arc<dyn GuestRegistry>,
+    outbox: Outbox,
+    config: SharedConfig,
+}
+
+impl Onboarding {
+    pub fn new(
+        users: Arc<dyn UserClient>,
+        projects: Arc<dyn ProjectClient>,
+        messaging: Arc<dyn MessagingClient>,
+        guests: Arc<dyn GuestRegistry>,
+        outbox: Outbox,
+        config: SharedConfig,
+    ) -> Self {
+        Onboarding {
+            users,
+            projects,
+            messaging,
+            guests,
+            outbox,
+            config,
+        }
+    }
+
+    pub async fn guest(&self, project_id: i32) -> Result<UserInClaims, AuthError> {
+        let mut done = Vec::new();
+
+        match self.run(project_id, &mut done).await {
+            Ok(user) => {
+                let body = json!({
+                    "event": "guest_created",
+                    "user_id": user.id,
+                    "project_id": project_id,
+                });
+                self.outbox.publish("notifications", body).await;
+                Ok(user)
+            }
+            Err(e) => {
+                warn!("ONBOARDING: guest for project {project_id} failed; {e}");
+                self.compensate(done).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn run(
+        &self,
+        project_id: i32,
+        done: &mut Vec<Done>,
+    ) -> Result<UserInClaims, AuthError> {
+        let new_user = PubNewUser {
+            depends_on: 1,
+            role_id: Role::Guest.id(),
+            active: Some(true),
+            project_id,
+        };
+
+        let user = retry(|| self.users.create(&new_user)).await?;
+        done.push(Done::User(user.id));
+
+        let user_id = user.id;
+        already_done(retry(|| self.projects.init_user(project_id, user_id)).await)?;
+        done.push(Done::Membership {
+            project_id,
+            user_id,
+        });
+
+        already_done(retry(|| self.messaging.init_user(user_id)).await)?;
+        done.push(Done::MessagingToken(user_id));
+
+        let now = Utc::now();
+        let guest = Guest {
+            user_id,
+            project_id,
+            created_at: now,
+            expires_at: self
+                .config
+                .get()
+                .guest_lifetime(project_id)
+                .map(|lifetime| now + chrono::Duration::seconds(lifetime)),
+        };
+        self.guests.register(guest.clone()).await.map_err(|e| {
+            error!("ONBOARDING: guest {user_id} could not be registered; {e}");
+            AuthError::Http(Status::InternalServerError)
+        })?;
+
+        let mut user = UserInClaims::from(user);
+        user.expires_at = guest.expires_at.map(|at| at.timestamp());
+
+        Ok(user)
+    }
+
+    /// Whatever cannot be undone right away is left to the outbox.
+    async fn compensate(&self, done: Vec<Done>) {
+        let mut pending = Vec::new();
+        for step in done.into_iter().rev() {
+            let result = match &step {
+                Done::MessagingToken(user_id) => {
+                    retry(|| self.messaging.delete_user(*user_id)).await
+                }
+                Done::Membership {
+                    project_id,
+                    user_id,
+                } => retry(|| self.projects.remove_user(*project_id, *user_id)).await,
+                Done::User(user_id) => retry(|| self.users.delete(*user_id)).await,
+            };
+
+            match result {
+                Ok(()) => info!("ONBOARDING: undone {step:?}"),
+                Err(e) if e.kind == UpstreamErrorKind::Status(404) => {}
+                Err(e) => {
+                    warn!("ONBOARDING: {step:?} could not be undone, queued; {e}");
+                    pending.push(step.undo());
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            self.outbox.send(pending).await;
+        }
+    }
+}
+
+/// Retries requests that never reached the service, the only ones that are
+/// safe to repeat for a create.
+async fn retry<T, F, Fut>(step: F) -> Result<T, UpstreamError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, UpstreamError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match step().await {
+            Err(e) if e.kind == UpstreamErrorKind::Connect && attempt < ATTEMPTS => {
+                attempt += 1
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A 409 means an earlier attempt already did it.
+fn already_done<T>(result: Result<T, UpstreamError>) -> Result<(), UpstreamError> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind == UpstreamErrorKind::Status(409) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Onboarding {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let rocket = request.rocket();
+
+        match (
+            rocket.state::<Arc<dyn UserClient>>(),
+            rocket.state::<Arc<dyn ProjectClient>>(),
+            rocket.state::<Arc<dyn MessagingClient>>(),
+            rocket.state::<Arc<dyn GuestRegistry>>(),
+            rocket.state::<Outbox>(),
+            rocket.state::<SharedConfig>(),
+        ) {
+            (
+                Some(users),
+                Some(projects),
+                Some(messaging),
+                Some(guests),
+                Some(outbox),
+                Some(config),
+            ) => Outcome::Success(Onboarding::new(
+                users.clone(),
+                projects.clone(),
+                messaging.clone(),
+                guests.clone(),
+                outbox.clone(),
+                config.clone(),
+            )),
+            _ => {
+                let error = AuthError::Http(Status::InternalServerError);
+                error.stash(request);
+                Outcome::Error((error.status(), error))
+            }
+        }
+    }
+}
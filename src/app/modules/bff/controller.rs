@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use reqwest::Method;
+use rocket::data::{Data, Limits, ToByteUnit};
+use rocket::http::{CookieJar, Status};
+use rocket::serde::json::Json;
+use rocket::{Build, Rocket, State};
+
+use crate::app::modules::auth::services::helpers;
+use crate::app::modules::auth::services::onboarding::Onboarding;
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::guards::audit::Audit;
+use crate::app::providers::guards::csrf::CsrfChecked;
+use crate::app::providers::guards::idempotency::IdempotencyKey;
+use crate::app::providers::services::audit::EventKind;
+use crate::app::providers::services::claims::UserInClaims;
+use crate::app::providers::services::clients::{ProfileClient, UserClient};
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::outbox::{Effect, Outbox};
+
+use super::services::proxy::{self, ProxyRequest, ProxyResponse};
+use super::services::session::{ActiveSession, BffConfig, Session, SessionStore};
+
+/// Mounts the session routes at `/bff` and the proxy at `/api` when
+/// `bff.enabled` is set.
+pub async fn mount(rocket: Rocket<Build>) -> Result<Rocket<Build>, Rocket<Build>> {
+    let bff = match rocket.figment().extract_inner::<BffConfig>("bff") {
+        Ok(bff) => bff,
+        Err(e) if e.missing() => BffConfig::default(),
+        Err(e) => {
+            error!("ERROR: bff; invalid bff config; {e}");
+            return Err(rocket);
+        }
+    };
+
+    if !bff.enabled {
+        return Ok(rocket);
+    }
+
+    Ok(rocket
+        .manage(bff)
+        .manage(SessionStore::default())
+        .mount("/bff", routes![login, me, logout])
+        .mount(
+            "/api",
+            routes![proxy_get, proxy_post, proxy_put, proxy_patch, proxy_delete],
+        ))
+}
+
+/// The session user, without the token only refresh tokens carry.
+fn public_user(mut user: UserInClaims) -> Json<UserInClaims> {
+    user.user_token = None;
+    Json(user)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[post("/login", data = "<token>")]
+pub async fn login(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    store: &State<SessionStore>,
+    profile: &State<Arc<dyn ProfileClient>>,
+    users: &State<Arc<dyn UserClient>>,
+    cookie: &CookieJar<'_>,
+    onboarding: Onboarding,
+    audit: Audit,
+    idempotency: IdempotencyKey,
+    token: Json<String>,
+) -> Result<Json<UserInClaims>, AuthError> {
+    let config = config.get();
+    let token = token.into_inner();
+    let kind = match helpers::guest_project(&token) {
+        Ok(Some(_)) => EventKind::GuestCreated,
+        _ => EventKind::Login,
+    };
+    let ttl = bff.session_ttl(&config);
+
+    // Only the user is remembered, a replay opens a session of its own
+    let logged_in = idempotency
+        .once(token.as_bytes(), async {
+            let user_in_claims = helpers::login_request(
+                profile.as_ref(),
+                users.as_ref(),
+                &onboarding,
+                token.clone(),
+            )
+            .await;
+            let user_id = user_in_claims.as_ref().ok().map(|user| user.id);
+            audit.track(kind, user_id, user_id, user_in_claims).await
+        })
+        .await?;
+    let replayed = logged_in.replayed();
+    let user_in_claims = logged_in.into_inner();
+
+    let session = Session::open(&config, user_in_claims.clone(), ttl).await?;
+    let id = store.create(session);
+
+    // The first request recorded its own event, a replay is a plain login
+    if replayed {
+        let user_id = Some(user_in_claims.id);
+        audit
+            .record(EventKind::Login, user_id, user_id, Ok(()))
+            .await;
+    }
+
+    let mut session_cookie =
+        config
+            .cookie
+            .build_at("/".to_string(), bff.session_cookie.clone(), id, ttl);
+    session_cookie.set_http_only(true);
+    cookie.add_private(session_cookie);
+
+    Ok(public_user(user_in_claims))
+}
+
+#[get("/me")]
+pub async fn me(session: ActiveSession) -> Json<UserInClaims> {
+    public_user(session.session.user)
+}
+
+#[post("/logout")]
+pub async fn logout(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    store: &State<SessionStore>,
+    cookie: &CookieJar<'_>,
+    audit: Audit,
+    _csrf: CsrfChecked,
+    session: ActiveSession,
+) -> Status {
+    store.remove(&session.id);
+    let user_id = session.session.user.id;
+    let effects = vec![Effect::ResetTokens { user_id }];
+    audit
+        .record_with(
+            EventKind::Logout,
+            Some(user_id),
+            Some(user_id),
+            Ok(()),
+            effects,
+        )
+        .await;
+
+    let removal = config.get().cookie.build_at(
+        "/".to_string(),
+        bff.session_cookie.clone(),
+        String::new(),
+        0,
+    );
+    cookie.remove_private(removal);
+
+    Status::Ok
+}
+
+struct Proxy<'a> {
+    config: &'a State<SharedConfig>,
+    bff: &'a State<BffConfig>,
+    fetch: &'a State<Fetch>,
+    session: ActiveSession,
+    request: ProxyRequest,
+}
+
+impl Proxy<'_> {
+    async fn forward(
+        self,
+        method: Method,
+        data: Option<(Data<'_>, &Limits)>,
+    ) -> Result<ProxyResponse, AuthError> {
+        let data =
+            data.map(|(data, limits)| (data, limits.get("bff").unwrap_or(2.mebibytes())));
+
+        proxy::forward(
+            self.fetch,
+            &self.config.get(),
+            self.bff,
+            &self.session.session.access_token,
+            method,
+            self.request,
+            data,
+        )
+        .await
+    }
+}
+
+#[get("/<_>/<_..>")]
+pub async fn proxy_get(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    fetch: &State<Fetch>,
+    session: ActiveSession,
+    request: ProxyRequest,
+) -> Result<ProxyResponse, AuthError> {
+    Proxy {
+        config,
+        bff,
+        fetch,
+        session,
+        request,
+    }
+    .forward(Method::GET, None)
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+#[post("/<_>/<_..>", data = "<data>")]
+pub async fn proxy_post(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    fetch: &State<Fetch>,
+    limits: &Limits,
+    _csrf: CsrfChecked,
+    session: ActiveSession,
+    request: ProxyRequest,
+    data: Data<'_>,
+) -> Result<ProxyResponse, AuthError> {
+    Proxy {
+        config,
+        bff,
+        fetch,
+        session,
+        request,
+    }
+    .forward(Method::POST, Some((data, limits)))
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+#[put("/<_>/<_..>", data = "<data>")]
+pub async fn proxy_put(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    fetch: &State<Fetch>,
+    limits: &Limits,
+    _csrf: CsrfChecked,
+    session: ActiveSession,
+    request: ProxyRequest,
+    data: Data<'_>,
+) -> Result<ProxyResponse, AuthError> {
+    Proxy {
+        config,
+        bff,
+        fetch,
+        session,
+        request,
+    }
+    .forward(Method::PUT, Some((data, limits)))
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+#[patch("/<_>/<_..>", data = "<data>")]
+pub async fn proxy_patch(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    fetch: &State<Fetch>,
+    limits: &Limits,
+    _csrf: CsrfChecked,
+    session: ActiveSession,
+    request: ProxyRequest,
+    data: Data<'_>,
+) -> Result<ProxyResponse, AuthError> {
+    Proxy {
+        config,
+        bff,
+        fetch,
+        session,
+        request,
+    }
+    .forward(Method::PATCH, Some((data, limits)))
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+#[delete("/<_>/<_..>", data = "<data>")]
+pub async fn proxy_delete(
+    config: &State<SharedConfig>,
+    bff: &State<BffConfig>,
+    fetch: &State<Fetch>,
+    limits: &Limits,
+    _csrf: CsrfChecked,
+    session: ActiveSession,
+    request: ProxyRequest,
+    data: Data<'_>,
+) -> Result<ProxyResponse, AuthError> {
+    Proxy {
+        config,
+        bff,
+        fetch,
+        session,
+        request,
+    }
+    .forward(Method::DELETE, Some((data, limits)))
+    .await
+}
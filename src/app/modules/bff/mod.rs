@@ -0,0 +1,2 @@
+pub mod controller;
+mod services;
@@ -0,0 +1,161 @@
+use reqwest::Method;
+use rocket::data::{ByteUnit, Data};
+use rocket::http::{ContentType, RawStr, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+
+use crate::app::providers::config::AuthConfig;
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::UpstreamError;
+
+use super::session::BffConfig;
+
+const FORWARDED_HEADERS: [&str; 3] = ["Content-Type", "Accept", "Accept-Language"];
+
+/// What is forwarded from `/api/<service>/<path..>`: the still encoded path
+/// below the service, the query and a few content negotiation headers. Paths
+/// with dot segments are refused, they could climb out of the service url.
+pub struct ProxyRequest {
+    pub service: String,
+    pub path: String,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ProxyRequest {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // Mounted at /api, so the path is /api/<service>/<rest>
+        let mut parts = request.uri().path().as_str().splitn(4, '/').skip(2);
+        let service = parts.next().unwrap_or_default().to_string();
+        let mut path = parts.next().unwrap_or_default().to_string();
+
+        if path.split('/').any(escapes) {
+            let error =
+                AuthError::InvalidRequest("The path may not contain dot segments".to_string());
+            error.stash(request);
+            return Outcome::Error((error.status(), error));
+        }
+
+        if let Some(query) = request.uri().query() {
+            path = format!("{path}?{query}");
+        }
+
+        let headers = FORWARDED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                request
+                    .headers()
+                    .get_one(name)
+                    .map(|value| (*name, value.to_string()))
+            })
+            .collect();
+
+        Outcome::Success(ProxyRequest {
+            service,
+            path,
+            headers,
+        })
+    }
+}
+
+/// Dot segments, percent-encoded ones included, and segments hiding a
+/// slash behind an escape.
+fn escapes(segment: &str) -> bool {
+    let segment = RawStr::new(segment).percent_decode_lossy();
+
+    matches!(segment.as_ref(), "." | "..") || segment.contains(['/', '\\'])
+}
+
+/// Downstream answer, passed through whatever its status.
+pub struct ProxyResponse {
+    pub status: Status,
+    pub content_type: Option<ContentType>,
+    pub body: Vec<u8>,
+}
+
+impl<'r> Responder<'r, 'static> for ProxyResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+        response.status(self.status);
+
+        if let Some(content_type) = self.content_type {
+            response.header(content_type);
+        }
+
+        Ok(response
+            .sized_body(self.body.len(), std::io::Cursor::new(self.body))
+            .finalize())
+    }
+}
+
+pub async fn forward(
+    fetch: &Fetch,
+    config: &AuthConfig,
+    bff: &BffConfig,
+    access_token: &str,
+    method: Method,
+    request: ProxyRequest,
+    data: Option<(Data<'_>, ByteUnit)>,
+) -> Result<ProxyResponse, AuthError> {
+    let (service, base_url) = match config.entity(&request.service) {
+        Some(entity) if bff.proxies(&request.service) => entity,
+        _ => return Err(AuthError::Http(Status::NotFound)),
+    };
+
+    // ProxyRequest refuses dot segments, whatever else the url parser
+    // normalizes must still land below the service url
+    let url = format!("{base_url}{}", request.path);
+    let url = match (reqwest::Url::parse(base_url), reqwest::Url::parse(&url)) {
+        (Ok(base), Ok(url)) if url.as_str().starts_with(base.as_str()) => url,
+        _ => return Err(AuthError::Http(Status::BadRequest)),
+    };
+
+    let mut builder = fetch
+        .client
+        .request(method, url)
+        .timeout(fetch.timeout_for(service))
+        .bearer_auth(access_token);
+
+    for (name, value) in request.headers {
+        builder = builder.header(name, value);
+    }
+
+    if let Some((data, limit)) = data {
+        let body = data
+            .open(limit)
+            .into_bytes()
+            .await
+            .map_err(|_| AuthError::Http(Status::BadRequest))?;
+
+        if !body.is_complete() {
+            return Err(AuthError::Http(Status::PayloadTooLarge));
+        }
+
+        builder = builder.body(body.into_inner());
+    }
+
+    let res = builder
+        .send()
+        .await
+        .map_err(|e| UpstreamError::from_reqwest(service, e))?;
+
+    let status = Status::new(res.status().as_u16());
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(ContentType::parse_flexible);
+    let body = res
+        .bytes()
+        .await
+        .map_err(|e| UpstreamError::from_reqwest(service, e))?;
+
+    Ok(ProxyResponse {
+        status,
+        content_type,
+        body: body.to_vec(),
+    })
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use rocket::request::{FromRequest, Outcome, Request};
+use serde::Deserialize;
+
+use crate::app::modules::auth::services::helpers;
+use crate::app::providers::config::{AuthConfig, SharedConfig};
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::guards::claims::RefreshClaims;
+use crate::app::providers::services::claims::UserInClaims;
+use crate::app::providers::services::clients::UserClient;
+use crate::app::providers::services::cookie::random_token;
+use crate::app::providers::services::revocation::Revocations;
+use crate::app::providers::services::token::Token;
+
+const SESSION_ID_LEN: usize = 43;
+
+/// The `bff` table of Rocket.toml. Sessions default to the refresh token
+/// lifetime and `services` empty proxies every configured entity.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct BffConfig {
+    pub enabled: bool,
+    pub session_cookie: String,
+    pub session_ttl: Option<i64>,
+    pub refresh_leeway: i64,
+    pub services: Vec<String>,
+}
+
+impl Default for BffConfig {
+    fn default() -> Self {
+        BffConfig {
+            enabled: false,
+            session_cookie: "session".to_string(),
+            session_ttl: None,
+            refresh_leeway: 60,
+            services: Vec::new(),
+        }
+    }
+}
+
+impl BffConfig {
+    pub fn session_ttl(&self, config: &AuthConfig) -> i64 {
+        self.session_ttl.unwrap_or(config.refresh_token_expiration)
+    }
+
+    pub fn proxies(&self, service: &str) -> bool {
+        self.services.is_empty() || self.services.iter().any(|s| s == service)
+    }
+}
+
+/// Tokens kept server side for one browser session.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub user: UserInClaims,
+    pub access_token: String,
+    pub access_expires_at: i64,
+    pub refresh_token: String,
+    pub issued_at: i64,
+    pub expires_at: i64,
+}
+
+impl Session {
+    pub async fn open(
+        config: &AuthConfig,
+        user: UserInClaims,
+        ttl: i64,
+    ) -> Result<Self, AuthError> {
+        let (refresh_token, access_token) =
+            helpers::token_generator(config, user.clone()).await?;
+        let now = chrono::Utc::now().timestamp();
+
+        Ok(Session {
+            user,
+            access_token,
+            access_expires_at: now + config.access_token_expiration,
+            refresh_token,
+            issued_at: now,
+            expires_at: now + ttl,
+        })
+    }
+
+    /// Rotates the tokens through the same validation as `GET /auth`.
+    async fn refresh(
+        &mut self,
+        config: &AuthConfig,
+        revocations: &Revocations,
+        users: &dyn UserClient,
+    ) -> Result<(), AuthError> {
+        let token = Token(self.refresh_token.clone());
+        let claims = RefreshClaims::from_token(config, revocations, token).await?;
+        let user = claims.0.user;
+        let user =
+            helpers::refresh_user(users, &user, user.project.as_ref().map(|p| p.id)).await?;
+        let (refresh_token, access_token) =
+            helpers::token_generator(config, user.clone()).await?;
+
+        self.user = user;
+        self.access_token = access_token;
+        self.issued_at = chrono::Utc::now().timestamp();
+        self.access_expires_at = self.issued_at + config.access_token_expiration;
+        self.refresh_token = refresh_token;
+
+        Ok(())
+    }
+}
+
+/// In memory session store. Sessions live in this instance only, so run a
+/// single replica or pin clients to one when the BFF mode is enabled.
+#[derive(Clone, Default)]
+pub struct SessionStore(Arc<RwLock<HashMap<String, Session>>>);
+
+impl SessionStore {
+    pub fn create(&self, session: Session) -> String {
+        let id = random_token(SESSION_ID_LEN);
+        let now = chrono::Utc::now().timestamp();
+
+        let mut sessions = self.0.write().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(id.clone(), session);
+
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<Session> {
+        let now = chrono::Utc::now().timestamp();
+        let sessions = self.0.read().unwrap_or_else(|e| e.into_inner());
+
+        sessions
+            .get(id)
+            .filter(|session| session.expires_at > now)
+            .cloned()
+    }
+
+    pub fn update(&self, id: &str, session: Session) {
+        let mut sessions = self.0.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(current) = sessions.get_mut(id) {
+            *current = session;
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> Option<Session> {
+        self.0.write().unwrap_or_else(|e| e.into_inner()).remove(id)
+    }
+}
+
+/// Session named by the session cookie, with an access token that is valid
+/// for at least `refresh_leeway` more seconds.
+pub struct ActiveSession {
+    pub id: String,
+    pub session: Session,
+}
+
+fn fail(request: &Request<'_>, error: AuthError) -> Outcome<ActiveSession, AuthError> {
+    error.stash(request);
+
+    Outcome::Error((error.status(), error))
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ActiveSession {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let rocket = request.rocket();
+        let (bff, store, revocations, config, users) = match (
+            rocket.state::<BffConfig>(),
+            rocket.state::<SessionStore>(),
+            rocket.state::<Revocations>(),
+            rocket.state::<SharedConfig>(),
+            rocket.state::<Arc<dyn UserClient>>(),
+        ) {
+            (Some(bff), Some(store), Some(revocations), Some(config), Some(users)) => {
+                (bff, store, revocations, config.get(), users)
+            }
+            _ => {
+                return fail(
+                    request,
+                    AuthError::Http(rocket::http::Status::InternalServerError),
+                )
+            }
+        };
+
+        let id = match request.cookies().get_private(&bff.session_cookie) {
+            Some(cookie) => cookie.value().to_string(),
+            None => return fail(request, AuthError::MissingSession),
+        };
+
+        let mut session = match store.get(&id) {
+            Some(session) => session,
+            None => return fail(request, AuthError::MissingSession),
+        };
+
+        let project_id = session.user.project.as_ref().map(|project| project.id);
+        match revocations
+            .is_revoked(session.user.id, project_id, session.issued_at)
+            .await
+        {
+            Ok(false) => {}
+            Ok(true) => {
+                info!("BFF: session of revoked user {} dropped", session.user.id);
+                store.remove(&id);
+                return fail(request, AuthError::MissingSession);
+            }
+            Err(e) => return fail(request, e),
+        }
+
+        if session.access_expires_at - bff.refresh_leeway <= chrono::Utc::now().timestamp() {
+            match session.refresh(&config, revocations, users.as_ref()).await {
+                Ok(()) => store.update(&id, session.clone()),
+                Err(AuthError::Upstream(e)) => return fail(request, AuthError::Upstream(e)),
+                Err(e) => {
+                    info!("BFF: session dropped; {e}");
+                    store.remove(&id);
+                    return fail(request, AuthError::MissingSession);
+                }
+            }
+        }
+
+        Outcome::Success(ActiveSession { id, session })
+    }
+}
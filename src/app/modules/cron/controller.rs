@@ -0,0 +1,265 @@
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::app::modules::cron::model::{CronJob, CronJobComplete, CronRun, NewCronJob};
+use crate::app::modules::cron::services::{repository, runner};
+use crate::app::modules::escalon::model::{EJob, NewEJob};
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::guards::OperatorClaims;
+use crate::app::providers::services::cron::CronManager;
+
+const RUNS_PAGE: i64 = 50;
+const RUNS_MAX_PAGE: i64 = 1000;
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![index, show, create, update, delete, runs, pause, resume, trigger]
+}
+
+fn internal(action: &str, e: String) -> AuthError {
+    error!("CRON: {action} failed; {e}");
+    AuthError::Http(Status::InternalServerError)
+}
+
+fn validate(config: &SharedConfig, new_cronjob: &NewCronJob) -> Result<(), AuthError> {
+    if config.get().entity_url(&new_cronjob.service).is_none() {
+        return Err(AuthError::InvalidRequest(format!(
+            "service `{}` has no url configured",
+            new_cronjob.service
+        )));
+    }
+
+    new_cronjob.validate().map_err(AuthError::InvalidRequest)
+}
+
+/// Drops the escalon job from this node's manager, if it runs here. Jobs
+/// of other nodes stop on their next tick, once their row is gone.
+async fn unschedule(cron: &CronManager, job_id: rocket::serde::uuid::Uuid) {
+    if cron.inner().get_job(job_id).await.is_some() {
+        cron.inner().remove_job(job_id).await;
+    }
+}
+
+async fn find(cron: &CronManager, id: i32) -> Result<CronJobComplete, AuthError> {
+    match repository::get_by_id(&cron.inner().context.db, id).await {
+        Ok(Some(cronjob)) => Ok(cronjob),
+        Ok(None) => Err(AuthError::Http(Status::NotFound)),
+        Err(e) => Err(internal(&format!("reading job {id}"), e)),
+    }
+}
+
+#[get("/")]
+pub async fn index(
+    cron: &State<CronManager>,
+    _operator: OperatorClaims,
+) -> Result<Json<Vec<CronJobComplete>>, AuthError> {
+    repository::get_all(&cron.inner().context.db)
+        .await
+        .map(Json)
+        .map_err(|e| internal("listing jobs", e))
+}
+
+#[get("/<id>")]
+pub async fn show(
+    cron: &State<CronManager>,
+    _operator: OperatorClaims,
+    id: i32,
+) -> Result<Json<CronJobComplete>, AuthError> {
+    find(cron, id).await.map(Json)
+}
+
+/// Schedules the job on this node, which becomes its owner.
+#[post("/", data = "<new_cronjob>")]
+pub async fn create(
+    cron: &State<CronManager>,
+    config: &State<SharedConfig>,
+    _operator: OperatorClaims,
+    new_cronjob: Json<NewCronJob>,
+) -> Result<(Status, Json<CronJobComplete>), AuthError> {
+    let new_cronjob = new_cronjob.into_inner();
+    validate(config, &new_cronjob)?;
+
+    let job = EJob::scheduled(
+        cron.inner().add_job(new_cronjob.job.clone()).await,
+        new_cronjob.job.clone(),
+    );
+    let owner = config.get().identity.clone();
+
+    match repository::create(&cron.inner().context.db, &owner, &new_cronjob, &job).await {
+        Ok(cronjob) => Ok((Status::Created, Json(cronjob))),
+        Err(e) => {
+            unschedule(cron, job.id).await;
+            Err(internal("creating a job", e))
+        }
+    }
+}
+
+/// Replaces the job with a new escalon job owned by this node.
+#[put("/<id>", data = "<new_cronjob>")]
+pub async fn update(
+    cron: &State<CronManager>,
+    config: &State<SharedConfig>,
+    _operator: OperatorClaims,
+    id: i32,
+    new_cronjob: Json<NewCronJob>,
+) -> Result<Json<CronJobComplete>, AuthError> {
+    let new_cronjob = new_cronjob.into_inner();
+    validate(config, &new_cronjob)?;
+
+    let previous = find(cron, id).await?;
+
+    let job = EJob::scheduled(
+        cron.inner().add_job(new_cronjob.job.clone()).await,
+        new_cronjob.job.clone(),
+    );
+    let owner = config.get().identity.clone();
+
+    match repository::update(&cron.inner().context.db, id, &owner, &new_cronjob, &job).await {
+        Ok(Some(cronjob)) => {
+            unschedule(cron, previous.job.id).await;
+            Ok(Json(cronjob))
+        }
+        Ok(None) => {
+            unschedule(cron, job.id).await;
+            Err(AuthError::Http(Status::NotFound))
+        }
+        Err(e) => {
+            unschedule(cron, job.id).await;
+            Err(internal(&format!("updating job {id}"), e))
+        }
+    }
+}
+
+#[delete("/<id>")]
+pub async fn delete(
+    cron: &State<CronManager>,
+    _operator: OperatorClaims,
+    id: i32,
+) -> Result<Status, AuthError> {
+    match repository::delete(&cron.inner().context.db, id).await {
+        Ok(Some(cronjob)) => {
+            unschedule(cron, cronjob.job_id).await;
+            Ok(Status::NoContent)
+        }
+        Ok(None) => Err(AuthError::Http(Status::NotFound)),
+        Err(e) => Err(internal(&format!("deleting job {id}"), e)),
+    }
+}
+
+/// The latest runs of the job, newest first.
+#[get("/<id>/runs?<limit>")]
+pub async fn runs(
+    cron: &State<CronManager>,
+    _operator: OperatorClaims,
+    id: i32,
+    limit: Option<i64>,
+) -> Result<Json<Vec<CronRun>>, AuthError> {
+    let limit = limit.unwrap_or(RUNS_PAGE).clamp(1, RUNS_MAX_PAGE);
+    find(cron, id).await?;
+
+    repository::get_runs(&cron.inner().context.db, id, limit)
+        .await
+        .map(Json)
+        .map_err(|e| internal(&format!("reading the runs of job {id}"), e))
+}
+
+/// Stops the ticks of the job. Like a resume, this node takes the job over
+/// under a new escalon id, left `paused` and unscheduled; the copy of the
+/// previous owner finds no row on its next tick and stops.
+#[post("/<id>/pause")]
+pub async fn pause(
+    cron: &State<CronManager>,
+    config: &State<SharedConfig>,
+    _operator: OperatorClaims,
+    id: i32,
+) -> Result<Json<CronJobComplete>, AuthError> {
+    let cronjob = find(cron, id).await?;
+
+    if !cronjob
+        .job
+        .pausable(Utc::now())
+        .map_err(AuthError::InvalidRequest)?
+    {
+        return Ok(Json(cronjob));
+    }
+
+    let job = EJob {
+        id: rocket::serde::uuid::Uuid::from_u128(rand::random()),
+        status: "paused".to_string(),
+        ..cronjob.job.clone()
+    };
+    let owner = config.get().identity.clone();
+
+    match repository::reschedule(&cron.inner().context.db, id, &owner, &job).await {
+        Ok(Some(paused)) => {
+            unschedule(cron, cronjob.job.id).await;
+            Ok(Json(paused))
+        }
+        Ok(None) => Err(AuthError::Http(Status::NotFound)),
+        Err(e) => Err(internal(&format!("pausing job {id}"), e)),
+    }
+}
+
+/// Schedules a paused or failed job again on this node, which becomes its
+/// owner. The copy of the previous owner stops on its next tick.
+#[post("/<id>/resume")]
+pub async fn resume(
+    cron: &State<CronManager>,
+    config: &State<SharedConfig>,
+    _operator: OperatorClaims,
+    id: i32,
+) -> Result<Json<CronJobComplete>, AuthError> {
+    let cronjob = find(cron, id).await?;
+
+    if !cronjob
+        .job
+        .resumable(Utc::now())
+        .map_err(AuthError::InvalidRequest)?
+    {
+        return Ok(Json(cronjob));
+    }
+
+    let new_ejob = NewEJob::from(cronjob.job.clone());
+    let job = EJob::scheduled(cron.inner().add_job(new_ejob.clone()).await, new_ejob);
+    let owner = config.get().identity.clone();
+
+    match repository::reschedule(&cron.inner().context.db, id, &owner, &job).await {
+        Ok(Some(resumed)) => {
+            unschedule(cron, cronjob.job.id).await;
+            Ok(Json(resumed))
+        }
+        Ok(None) => {
+            unschedule(cron, job.id).await;
+            Err(AuthError::Http(Status::NotFound))
+        }
+        Err(e) => {
+            unschedule(cron, job.id).await;
+            Err(internal(&format!("resuming job {id}"), e))
+        }
+    }
+}
+
+/// Runs the job now on this node, paused or not, as long as its
+/// `since`/`until` window is open. The run shows up in its runs.
+#[post("/<id>/trigger")]
+pub async fn trigger(
+    cron: &State<CronManager>,
+    _operator: OperatorClaims,
+    id: i32,
+) -> Result<Status, AuthError> {
+    let cronjob = find(cron, id).await?;
+    cronjob
+        .job
+        .triggerable(Utc::now())
+        .map_err(AuthError::InvalidRequest)?;
+
+    let context = cron.inner().context.clone();
+    let cronjob = CronJob::from(&cronjob);
+    rocket::tokio::spawn(async move {
+        runner::run(&context, &cronjob, "manual").await;
+    });
+
+    Ok(Status::Accepted)
+}
@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::json::Value;
+use rocket_db_pools::sqlx::{self, types::Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::app::modules::escalon::model::{EJob, NewEJob};
+use crate::app::providers::models::cronjob::{self, PubCronJob, PubEJob, PubNewCronJob};
+
+const METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+const MAX_TIMEOUT: i32 = 300;
+const MAX_RETRY_ATTEMPTS: i32 = 10;
+const MAX_RETRY_BACKOFF: i32 = 3600;
+
+/// What happens to the ticks a job missed while no node scheduled it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misfire {
+    /// A single run for all of them.
+    FireOnce,
+    Skip,
+    /// A run per missed tick, oldest first.
+    CatchUp,
+}
+
+impl Misfire {
+    pub fn parse(misfire: &str) -> Option<Self> {
+        match misfire {
+            "fire_once" => Some(Misfire::FireOnce),
+            "skip" => Some(Misfire::Skip),
+            "catch_up" => Some(Misfire::CatchUp),
+            _ => None,
+        }
+    }
+}
+
+/// A row of `cronjobs`: the request a job sends to a service, and the node
+/// that runs it. `timeout` is in seconds, the `fetch` one when missing. A
+/// failed request is retried `retry_attempts` times, the first after
+/// `retry_backoff` seconds and each next one after twice as long.
+#[derive(Debug, Clone, Deserialize, Serialize, sqlx::FromRow)]
+#[serde(crate = "rocket::serde")]
+pub struct CronJob {
+    pub id: i32,
+    pub owner: String,
+    pub service: String,
+    pub route: String,
+    pub method: String,
+    pub body: Option<Value>,
+    pub timeout: Option<i32>,
+    pub retry_attempts: i32,
+    pub retry_backoff: i32,
+    pub misfire: String,
+    pub created_at: DateTime<Utc>,
+    pub job_id: Uuid,
+}
+
+impl CronJob {
+    pub fn misfire(&self) -> Misfire {
+        Misfire::parse(&self.misfire).unwrap_or(Misfire::Skip)
+    }
+}
+
+/// A cron job with its `escalonjobs` row.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CronJobComplete {
+    pub id: i32,
+    pub owner: String,
+    pub service: String,
+    pub route: String,
+    pub method: String,
+    pub body: Option<Value>,
+    pub timeout: Option<i32>,
+    pub retry_attempts: i32,
+    pub retry_backoff: i32,
+    pub misfire: String,
+    pub created_at: DateTime<Utc>,
+    pub job: EJob,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewCronJob {
+    pub service: String,
+    pub route: String,
+    #[serde(default = "NewCronJob::default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub timeout: Option<i32>,
+    #[serde(default)]
+    pub retry_attempts: i32,
+    #[serde(default = "NewCronJob::default_retry_backoff")]
+    pub retry_backoff: i32,
+    #[serde(default = "NewCronJob::default_misfire")]
+    pub misfire: String,
+    pub job: NewEJob,
+}
+
+impl NewCronJob {
+    fn default_method() -> String {
+        "GET".to_string()
+    }
+
+    fn default_retry_backoff() -> i32 {
+        5
+    }
+
+    fn default_misfire() -> String {
+        "skip".to_string()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.service.trim().is_empty() {
+            return Err("service is required".to_string());
+        }
+        if self.route.starts_with('/') {
+            return Err("route is relative to the service url, drop the leading /".to_string());
+        }
+        if !METHODS.contains(&self.method.as_str()) {
+            return Err(format!("method must be one of {}", METHODS.join(", ")));
+        }
+        if self.body.is_some() && self.method == "GET" {
+            return Err("GET jobs cannot send a body".to_string());
+        }
+        if self
+            .timeout
+            .is_some_and(|timeout| !(1..=MAX_TIMEOUT).contains(&timeout))
+        {
+            return Err(format!(
+                "timeout must be between 1 and {MAX_TIMEOUT} seconds"
+            ));
+        }
+        if !(0..=MAX_RETRY_ATTEMPTS).contains(&self.retry_attempts) {
+            return Err(format!(
+                "retry_attempts must be between 0 and {MAX_RETRY_ATTEMPTS}"
+            ));
+        }
+        if !(1..=MAX_RETRY_BACKOFF).contains(&self.retry_backoff) {
+            return Err(format!(
+                "retry_backoff must be between 1 and {MAX_RETRY_BACKOFF} seconds"
+            ));
+        }
+        if Misfire::parse(&self.misfire).is_none() {
+            return Err("misfire must be fire_once, skip or catch_up".to_string());
+        }
+
+        self.job.validate()
+    }
+}
+
+impl From<&CronJobComplete> for CronJob {
+    fn from(complete: &CronJobComplete) -> Self {
+        CronJob {
+            id: complete.id,
+            owner: complete.owner.clone(),
+            service: complete.service.clone(),
+            route: complete.route.clone(),
+            method: complete.method.clone(),
+            body: complete.body.clone(),
+            timeout: complete.timeout,
+            retry_attempts: complete.retry_attempts,
+            retry_backoff: complete.retry_backoff,
+            misfire: complete.misfire.clone(),
+            created_at: complete.created_at,
+            job_id: complete.job.id,
+        }
+    }
+}
+
+/// A row of `cron_runs`, one per attempt. `cause` is `schedule` for the
+/// ticks of the job, `misfire` for the runs replacing missed ones and
+/// `manual` for `POST /cron/<id>/trigger`.
+#[derive(Debug, Clone, Deserialize, Serialize, sqlx::FromRow)]
+#[serde(crate = "rocket::serde")]
+pub struct CronRun {
+    pub id: i64,
+    pub cronjob_id: i32,
+    pub cause: String,
+    pub attempt: i32,
+    pub node: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub http_status: Option<i32>,
+    pub success: bool,
+    pub excerpt: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewCronRun {
+    pub cronjob_id: i32,
+    pub cause: &'static str,
+    pub attempt: i32,
+    pub node: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub http_status: Option<i32>,
+    pub success: bool,
+    pub excerpt: Option<String>,
+}
+
+impl From<CronJobComplete> for PubCronJob {
+    fn from(cronjob: CronJobComplete) -> Self {
+        PubCronJob {
+            id: cronjob.id,
+            owner: cronjob.owner,
+            service: cronjob.service,
+            route: cronjob.route,
+            job: PubEJob {
+                id: cronjob.job.id,
+                status: cronjob.job.status,
+                schedule: cronjob.job.schedule,
+                timezone: cronjob.job.timezone,
+                run_at: cronjob.job.run_at,
+                since: cronjob.job.since,
+                until: cronjob.job.until,
+            },
+        }
+    }
+}
+
+impl From<PubNewCronJob> for NewCronJob {
+    fn from(cronjob: PubNewCronJob) -> Self {
+        NewCronJob {
+            service: cronjob.service,
+            route: cronjob.route,
+            method: NewCronJob::default_method(),
+            body: None,
+            timeout: None,
+            retry_attempts: 0,
+            retry_backoff: NewCronJob::default_retry_backoff(),
+            misfire: NewCronJob::default_misfire(),
+            job: NewEJob::from(cronjob.job),
+        }
+    }
+}
+
+impl From<cronjob::NewEJob> for NewEJob {
+    fn from(job: cronjob::NewEJob) -> Self {
+        NewEJob {
+            schedule: job.schedule,
+            timezone: job.timezone,
+            run_at: job.run_at,
+            since: job.since,
+            until: job.until,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use reqwest::Method;
+
+use crate::app::modules::cron::model::CronJob;
+use crate::app::providers::services::clients;
+use crate::app::providers::services::cron::Context;
+use crate::app::providers::services::upstream::{self, UpstreamError, UpstreamErrorKind};
+
+/// Status and start of the body of a successful answer.
+pub struct Answer {
+    pub status: u16,
+    pub excerpt: String,
+}
+
+/// Sends the request of `cronjob` to its service with a fresh robot token.
+/// Any answer other than 2xx is an error.
+pub async fn dispatch(context: &Context, cronjob: &CronJob) -> Result<Answer, UpstreamError> {
+    let config = context.config.get();
+    let service = match config.entity(&cronjob.service) {
+        Some((service, _)) => service,
+        // Validated on creation, the url may have been dropped since
+        None => return Err(UpstreamError::new("cron", UpstreamErrorKind::NotConfigured)),
+    };
+
+    let method = Method::from_bytes(cronjob.method.as_bytes()).map_err(|_| UpstreamError {
+        service,
+        kind: UpstreamErrorKind::Request,
+        excerpt: Some(format!("invalid method `{}`", cronjob.method)),
+    })?;
+
+    let mut request =
+        clients::robot_request(&context.fetch, &config, service, method, &cronjob.route)
+            .await?;
+
+    if let Some(timeout) = cronjob.timeout {
+        request = request.timeout(Duration::from_secs(timeout as u64));
+    }
+    if let Some(body) = &cronjob.body {
+        request = request.json(body);
+    }
+
+    let res = upstream::expect_success(service, request.send().await).await?;
+    let status = res.status().as_u16();
+    let body = res
+        .bytes()
+        .await
+        .map_err(|e| UpstreamError::from_reqwest(service, e))?;
+
+    Ok(Answer {
+        status,
+        excerpt: upstream::excerpt(&body),
+    })
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::app::modules::cron::model::{CronJob, Misfire};
+use crate::app::modules::cron::services::{repository, runner};
+use crate::app::modules::escalon::model::EJob;
+use crate::app::providers::services::cron::Context;
+use crate::app::providers::services::schedule::TzSchedule;
+
+/// Most runs a `catch_up` job makes up for at once.
+pub const MAX_CATCH_UP: usize = 100;
+
+/// Ticks of `job` after `last` and before `now` inside its `since`/`until`
+/// window, oldest first and at most `MAX_CATCH_UP`.
+pub fn missed(job: &EJob, last: DateTime<Utc>, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let in_window = |tick: &DateTime<Utc>| {
+        job.since.is_none_or(|since| *tick >= since)
+            && job.until.is_none_or(|until| *tick <= until)
+    };
+
+    if let Some(run_at) = job.run_at {
+        return match last < run_at && run_at < now && in_window(&run_at) {
+            true => vec![run_at],
+            false => Vec::new(),
+        };
+    }
+
+    let schedule = match TzSchedule::parse(&job.schedule, &job.timezone) {
+        Ok(schedule) => schedule,
+        Err(_) => return Vec::new(),
+    };
+
+    let after = job
+        .since
+        .map_or(last, |since| last.max(since - Duration::seconds(1)));
+    let before = job
+        .until
+        .map_or(now, |until| now.min(until + Duration::seconds(1)));
+
+    schedule
+        .ticks(after, before, MAX_CATCH_UP)
+        .into_iter()
+        .filter(in_window)
+        .collect()
+}
+
+/// Makes up for the ticks the cron job missed before this node took it
+/// over, as its misfire policy says. One-shot jobs whose time has passed
+/// are `done` afterwards whatever the policy.
+pub async fn recover(context: Context, cronjob_id: i32) {
+    let complete = match repository::get_by_id(&context.db, cronjob_id).await {
+        Ok(Some(complete)) => complete,
+        Ok(None) => return,
+        Err(e) => {
+            error!("CRON: job {cronjob_id} could not be read; {e}");
+            return;
+        }
+    };
+
+    let cronjob = CronJob::from(&complete);
+    let job = &complete.job;
+    let now = Utc::now();
+
+    if cronjob.misfire() != Misfire::Skip {
+        let last = match repository::last_run_at(&context.db, cronjob_id).await {
+            Ok(last) => last.unwrap_or(cronjob.created_at),
+            Err(e) => {
+                error!("CRON: last run of job {cronjob_id} could not be read; {e}");
+                return;
+            }
+        };
+
+        let ticks = missed(job, last, now);
+        if !ticks.is_empty() {
+            info!(
+                "CRON: job {cronjob_id} missed {} ticks since {last}",
+                ticks.len()
+            );
+        }
+
+        let runs = match cronjob.misfire() {
+            Misfire::FireOnce => ticks.len().min(1),
+            Misfire::CatchUp => ticks.len(),
+            Misfire::Skip => 0,
+        };
+        for _ in 0..runs {
+            runner::run(&context, &cronjob, "misfire").await;
+        }
+    }
+
+    if job.run_at.is_some_and(|run_at| run_at < now) {
+        if let Err(e) = repository::set_status(&context.db, job.id, "done").await {
+            error!("CRON: job {cronjob_id} could not be marked done; {e}");
+        }
+    }
+}
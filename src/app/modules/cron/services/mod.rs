@@ -0,0 +1,4 @@
+pub mod dispatch;
+pub mod misfire;
+pub mod repository;
+pub mod runner;
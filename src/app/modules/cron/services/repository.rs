@@ -0,0 +1,435 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::json::Value;
+use rocket_db_pools::sqlx::{self, types::Uuid, PgPool};
+
+use crate::app::modules::cron::model::{
+    CronJob, CronJobComplete, CronRun, NewCronJob, NewCronRun,
+};
+use crate::app::modules::escalon::model::EJob;
+
+const SELECT_COMPLETE: &str = "\
+    SELECT cronjobs.id, cronjobs.owner, cronjobs.service, cronjobs.route, \
+           cronjobs.method, cronjobs.body, cronjobs.timeout, cronjobs.retry_attempts, \
+           cronjobs.retry_backoff, cronjobs.misfire, cronjobs.created_at, \
+           escalonjobs.id AS job_id, escalonjobs.status, escalonjobs.schedule, \
+           escalonjobs.timezone, escalonjobs.run_at, escalonjobs.since, escalonjobs.until \
+    FROM cronjobs INNER JOIN escalonjobs ON escalonjobs.id = cronjobs.job_id";
+
+const SELECT_PENDING: &str = "\
+    SELECT cronjobs.id AS cronjob_id, escalonjobs.* \
+    FROM cronjobs INNER JOIN escalonjobs ON escalonjobs.id = cronjobs.job_id";
+
+/// An escalon job with the id of its cron job.
+#[derive(sqlx::FromRow)]
+struct PendingRow {
+    cronjob_id: i32,
+    #[sqlx(flatten)]
+    job: EJob,
+}
+
+#[derive(sqlx::FromRow)]
+struct CompleteRow {
+    id: i32,
+    owner: String,
+    service: String,
+    route: String,
+    method: String,
+    body: Option<Value>,
+    timeout: Option<i32>,
+    retry_attempts: i32,
+    retry_backoff: i32,
+    misfire: String,
+    created_at: DateTime<Utc>,
+    job_id: Uuid,
+    status: String,
+    schedule: String,
+    timezone: String,
+    run_at: Option<DateTime<Utc>>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl From<CompleteRow> for CronJobComplete {
+    fn from(row: CompleteRow) -> Self {
+        CronJobComplete {
+            id: row.id,
+            owner: row.owner,
+            service: row.service,
+            route: row.route,
+            method: row.method,
+            body: row.body,
+            timeout: row.timeout,
+            retry_attempts: row.retry_attempts,
+            retry_backoff: row.retry_backoff,
+            misfire: row.misfire,
+            created_at: row.created_at,
+            job: EJob {
+                id: row.job_id,
+                status: row.status,
+                schedule: row.schedule,
+                timezone: row.timezone,
+                run_at: row.run_at,
+                since: row.since,
+                until: row.until,
+            },
+        }
+    }
+}
+
+pub async fn get_all(db: &PgPool) -> Result<Vec<CronJobComplete>, String> {
+    sqlx::query_as::<_, CompleteRow>(&format!("{SELECT_COMPLETE} ORDER BY cronjobs.id"))
+        .fetch_all(db)
+        .await
+        .map(|rows| rows.into_iter().map(CronJobComplete::from).collect())
+        .map_err(|e| e.to_string())
+}
+
+pub async fn get_by_id(db: &PgPool, id: i32) -> Result<Option<CronJobComplete>, String> {
+    sqlx::query_as::<_, CompleteRow>(&format!("{SELECT_COMPLETE} WHERE cronjobs.id = $1"))
+        .bind(id)
+        .fetch_optional(db)
+        .await
+        .map(|row| row.map(CronJobComplete::from))
+        .map_err(|e| e.to_string())
+}
+
+/// The cron job currently scheduled as the escalon job `job_id`.
+pub async fn find_by_job(db: &PgPool, job_id: Uuid) -> Result<Option<CronJob>, String> {
+    sqlx::query_as::<_, CronJob>("SELECT * FROM cronjobs WHERE job_id = $1")
+        .bind(job_id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Stores a job `owner` has just scheduled as `job`.
+pub async fn create(
+    db: &PgPool,
+    owner: &str,
+    new_cronjob: &NewCronJob,
+    job: &EJob,
+) -> Result<CronJobComplete, String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+    insert_job(&mut tx, job).await?;
+    let cronjob = sqlx::query_as::<_, CronJob>(
+        "INSERT INTO cronjobs (owner, service, route, method, body, timeout, retry_attempts, \
+         retry_backoff, misfire, job_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *",
+    )
+    .bind(owner)
+    .bind(&new_cronjob.service)
+    .bind(&new_cronjob.route)
+    .bind(&new_cronjob.method)
+    .bind(&new_cronjob.body)
+    .bind(new_cronjob.timeout)
+    .bind(new_cronjob.retry_attempts)
+    .bind(new_cronjob.retry_backoff)
+    .bind(&new_cronjob.misfire)
+    .bind(job.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(complete(cronjob, job))
+}
+
+/// Points the cron job at `job`, now scheduled by `owner`, and drops the
+/// escalon job it replaces.
+pub async fn update(
+    db: &PgPool,
+    id: i32,
+    owner: &str,
+    new_cronjob: &NewCronJob,
+    job: &EJob,
+) -> Result<Option<CronJobComplete>, String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+    insert_job(&mut tx, job).await?;
+    let previous =
+        sqlx::query_scalar::<_, Uuid>("SELECT job_id FROM cronjobs WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+
+    let cronjob = sqlx::query_as::<_, CronJob>(
+        "UPDATE cronjobs SET owner = $2, service = $3, route = $4, method = $5, body = $6, \
+         timeout = $7, retry_attempts = $8, retry_backoff = $9, misfire = $10, job_id = $11 \
+         WHERE id = $1 RETURNING *",
+    )
+    .bind(id)
+    .bind(owner)
+    .bind(&new_cronjob.service)
+    .bind(&new_cronjob.route)
+    .bind(&new_cronjob.method)
+    .bind(&new_cronjob.body)
+    .bind(new_cronjob.timeout)
+    .bind(new_cronjob.retry_attempts)
+    .bind(new_cronjob.retry_backoff)
+    .bind(&new_cronjob.misfire)
+    .bind(job.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    delete_job(&mut tx, previous).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(Some(complete(cronjob, job)))
+}
+
+/// Moves the cron job, request unchanged, to `job` now scheduled by
+/// `owner`, for a resume or a node taking it over.
+pub async fn reschedule(
+    db: &PgPool,
+    id: i32,
+    owner: &str,
+    job: &EJob,
+) -> Result<Option<CronJobComplete>, String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+    insert_job(&mut tx, job).await?;
+    let previous =
+        sqlx::query_scalar::<_, Uuid>("SELECT job_id FROM cronjobs WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+
+    sqlx::query("UPDATE cronjobs SET owner = $2, job_id = $3 WHERE id = $1")
+        .bind(id)
+        .bind(owner)
+        .bind(job.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    delete_job(&mut tx, previous).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    get_by_id(db, id).await
+}
+
+/// Jobs still to run with the id of their cron job, those scheduled by
+/// `owner` or, when `own` is false, by every other node.
+pub async fn pending(db: &PgPool, owner: &str, own: bool) -> Result<Vec<(i32, EJob)>, String> {
+    let owned = if own { "=" } else { "!=" };
+
+    sqlx::query_as::<_, PendingRow>(&format!(
+        "{SELECT_PENDING} WHERE cronjobs.owner {owned} $1 \
+         AND escalonjobs.status NOT IN ('done', 'failed', 'paused')"
+    ))
+    .bind(owner)
+    .fetch_all(db)
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|row| (row.cronjob_id, row.job))
+            .collect()
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// A page of the unpaused jobs of `owner`, for a node taking them over.
+pub async fn owned_by(
+    db: &PgPool,
+    owner: &str,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<(i32, EJob)>, String> {
+    sqlx::query_as::<_, PendingRow>(&format!(
+        "{SELECT_PENDING} WHERE cronjobs.owner = $1 AND escalonjobs.status != 'paused' \
+         ORDER BY cronjobs.id LIMIT $2 OFFSET $3"
+    ))
+    .bind(owner)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(db)
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|row| (row.cronjob_id, row.job))
+            .collect()
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Stores the status a manager reports for its job, unless the job was
+/// paused meanwhile.
+pub async fn record_status(db: &PgPool, job_id: Uuid, status: &str) -> Result<(), String> {
+    sqlx::query("UPDATE escalonjobs SET status = $2 WHERE id = $1 AND status != 'paused'")
+        .bind(job_id)
+        .bind(status)
+        .execute(db)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Drops an escalon job no cron job points at anymore.
+pub async fn drop_job(db: &PgPool, job_id: Uuid) -> Result<(), String> {
+    sqlx::query(
+        "DELETE FROM escalonjobs WHERE id = $1 \
+         AND NOT EXISTS (SELECT 1 FROM cronjobs WHERE job_id = $1)",
+    )
+    .bind(job_id)
+    .execute(db)
+    .await
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+pub async fn get_status(db: &PgPool, job_id: Uuid) -> Result<Option<String>, String> {
+    sqlx::query_scalar::<_, String>("SELECT status FROM escalonjobs WHERE id = $1")
+        .bind(job_id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub async fn set_status(db: &PgPool, job_id: Uuid, status: &str) -> Result<bool, String> {
+    sqlx::query("UPDATE escalonjobs SET status = $2 WHERE id = $1")
+        .bind(job_id)
+        .bind(status)
+        .execute(db)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Removes the cron job and its escalon job, returning what was deleted.
+pub async fn delete(db: &PgPool, id: i32) -> Result<Option<CronJob>, String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+    let cronjob =
+        sqlx::query_as::<_, CronJob>("DELETE FROM cronjobs WHERE id = $1 RETURNING *")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    if let Some(cronjob) = &cronjob {
+        delete_job(&mut tx, cronjob.job_id).await?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(cronjob)
+}
+
+pub async fn insert_run(db: &PgPool, run: &NewCronRun) -> Result<(), String> {
+    let duration_ms = (run.finished_at - run.started_at).num_milliseconds();
+
+    sqlx::query(
+        "INSERT INTO cron_runs (cronjob_id, cause, attempt, node, started_at, finished_at, \
+         duration_ms, http_status, success, excerpt) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+    )
+    .bind(run.cronjob_id)
+    .bind(run.cause)
+    .bind(run.attempt)
+    .bind(&run.node)
+    .bind(run.started_at)
+    .bind(run.finished_at)
+    .bind(duration_ms)
+    .bind(run.http_status)
+    .bind(run.success)
+    .bind(&run.excerpt)
+    .execute(db)
+    .await
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Runs of the cron job, newest first.
+pub async fn get_runs(
+    db: &PgPool,
+    cronjob_id: i32,
+    limit: i64,
+) -> Result<Vec<CronRun>, String> {
+    sqlx::query_as::<_, CronRun>(
+        "SELECT * FROM cron_runs WHERE cronjob_id = $1 \
+         ORDER BY started_at DESC, id DESC LIMIT $2",
+    )
+    .bind(cronjob_id)
+    .bind(limit)
+    .fetch_all(db)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// When the schedule of the cron job last ran, retries and manual triggers
+/// aside, so a trigger does not hide the ticks missed before it.
+pub async fn last_run_at(
+    db: &PgPool,
+    cronjob_id: i32,
+) -> Result<Option<DateTime<Utc>>, String> {
+    sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        "SELECT max(started_at) FROM cron_runs WHERE cronjob_id = $1 AND attempt = 1 \
+         AND cause IN ('schedule', 'misfire')",
+    )
+    .bind(cronjob_id)
+    .fetch_one(db)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+async fn insert_job(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    job: &EJob,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO escalonjobs (id, status, schedule, timezone, run_at, since, until) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(job.id)
+    .bind(&job.status)
+    .bind(&job.schedule)
+    .bind(&job.timezone)
+    .bind(job.run_at)
+    .bind(job.since)
+    .bind(job.until)
+    .execute(&mut **tx)
+    .await
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+async fn delete_job(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    job_id: Uuid,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM escalonjobs WHERE id = $1")
+        .bind(job_id)
+        .execute(&mut **tx)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn complete(cronjob: CronJob, job: &EJob) -> CronJobComplete {
+    CronJobComplete {
+        id: cronjob.id,
+        owner: cronjob.owner,
+        service: cronjob.service,
+        route: cronjob.route,
+        method: cronjob.method,
+        body: cronjob.body,
+        timeout: cronjob.timeout,
+        retry_attempts: cronjob.retry_attempts,
+        retry_backoff: cronjob.retry_backoff,
+        misfire: cronjob.misfire,
+        created_at: cronjob.created_at,
+        job: job.clone(),
+    }
+}
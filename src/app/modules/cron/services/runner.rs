@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::app::modules::cron::model::{CronJob, NewCronRun};
+use crate::app::modules::cron::services::{dispatch, repository};
+use crate::app::providers::services::cron::Context;
+use crate::app::providers::services::upstream::UpstreamErrorKind;
+
+pub const MAX_BACKOFF: u64 = 3600;
+
+/// Wait before `attempt`, the second one waits `retry_backoff` seconds.
+pub fn backoff(cronjob: &CronJob, attempt: i32) -> Duration {
+    let exponent = (attempt - 2).clamp(0, 16) as u32;
+    let secs = (cronjob.retry_backoff.max(1) as u64).saturating_mul(2u64.pow(exponent));
+
+    Duration::from_secs(secs.min(MAX_BACKOFF))
+}
+
+/// Sends the request of `cronjob` following its retry policy and records
+/// every attempt in `cron_runs`. True once an attempt succeeded.
+pub async fn run(context: &Context, cronjob: &CronJob, cause: &'static str) -> bool {
+    let node = context.config.get().identity.clone();
+
+    for attempt in 1..=cronjob.retry_attempts.max(0) + 1 {
+        if attempt > 1 {
+            rocket::tokio::time::sleep(backoff(cronjob, attempt)).await;
+        }
+
+        let started_at = Utc::now();
+        let outcome = dispatch::dispatch(context, cronjob).await;
+        let finished_at = Utc::now();
+
+        let (success, http_status, excerpt) = match &outcome {
+            Ok(answer) => (
+                true,
+                Some(answer.status as i32),
+                Some(answer.excerpt.clone()),
+            ),
+            Err(e) => {
+                let status = match e.kind {
+                    UpstreamErrorKind::Status(status) => Some(status as i32),
+                    _ => None,
+                };
+                (false, status, Some(e.to_string()))
+            }
+        };
+
+        match &outcome {
+            Ok(answer) => info!(
+                "CRON: job {} {} {} answered {}",
+                cronjob.id, cronjob.method, cronjob.route, answer.status
+            ),
+            Err(e) => warn!(
+                "CRON: job {} {} {} failed, attempt {attempt}; {e}",
+                cronjob.id, cronjob.method, cronjob.route
+            ),
+        }
+
+        let run = NewCronRun {
+            cronjob_id: cronjob.id,
+            cause,
+            attempt,
+            node: node.clone(),
+            started_at,
+            finished_at,
+            http_status,
+            success,
+            excerpt,
+        };
+        if let Err(e) = repository::insert_run(&context.db, &run).await {
+            error!("CRON: run of job {} could not be recorded; {e}", cronjob.id);
+        }
+
+        if success {
+            return true;
+        }
+    }
+
+    false
+}
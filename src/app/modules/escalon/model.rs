@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use escalon_jobs::{EscalonJob, EscalonJobStatus, EscalonJobTrait, NewEscalonJob};
+use rocket_db_pools::sqlx::{self, types::Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::app::modules::cron::services::{repository, runner};
+use crate::app::providers::services::cron::Context;
+use crate::app::providers::services::schedule::{self, TzSchedule};
+
+/// A row of `escalonjobs`, where the cluster keeps the state of a job.
+/// `schedule` is read on the wall clock of `timezone`; one-shot jobs have
+/// an empty one and a `run_at`.
+#[derive(Debug, Clone, Deserialize, Serialize, sqlx::FromRow)]
+#[serde(crate = "rocket::serde")]
+pub struct EJob {
+    pub id: Uuid,
+    pub status: String,
+    pub schedule: String,
+    pub timezone: String,
+    pub run_at: Option<DateTime<Utc>>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl EJob {
+    /// The row of `new_ejob`, just scheduled as `job`.
+    pub fn scheduled(job: EscalonJob, new_ejob: NewEJob) -> Self {
+        EJob {
+            id: job.job_id,
+            status: status(&job.status),
+            schedule: new_ejob.schedule,
+            timezone: new_ejob.timezone,
+            run_at: new_ejob.run_at,
+            since: new_ejob.since,
+            until: new_ejob.until,
+        }
+    }
+
+    /// Whether the job will not tick anymore, being `done` or past `until`.
+    pub fn has_ended(&self, now: DateTime<Utc>) -> bool {
+        self.status == "done" || self.until.is_some_and(|until| until <= now)
+    }
+
+    /// Whether `POST /cron/<id>/pause` has anything to pause.
+    pub fn pausable(&self, now: DateTime<Utc>) -> Result<bool, String> {
+        match self.status.as_str() {
+            "paused" => Ok(false),
+            _ if self.has_ended(now) => Err("The job is past its until date".to_string()),
+            _ => Ok(true),
+        }
+    }
+
+    /// Whether `POST /cron/<id>/resume` has anything to schedule again.
+    pub fn resumable(&self, now: DateTime<Utc>) -> Result<bool, String> {
+        match self.status.as_str() {
+            "paused" | "failed" if self.has_ended(now) => {
+                Err("The job is past its until date".to_string())
+            }
+            "paused" | "failed" => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Whether `POST /cron/<id>/trigger` may run the job now, paused or not.
+    pub fn triggerable(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.since.is_some_and(|since| since > now) {
+            return Err("The job has not reached its since date".to_string());
+        }
+        if self.has_ended(now) {
+            return Err("The job is past its until date".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// What a node needs to schedule a job. Jobs are handed between nodes as
+/// rows, so this is all they carry; the call itself is read from `cronjobs`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewEJob {
+    #[serde(default)]
+    pub schedule: String,
+    #[serde(default = "NewEJob::default_timezone")]
+    pub timezone: String,
+    #[serde(default)]
+    pub run_at: Option<DateTime<Utc>>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl NewEJob {
+    fn default_timezone() -> String {
+        "UTC".to_string()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.run_at {
+            Some(_) if !self.schedule.is_empty() => {
+                return Err("a job has either a schedule or a run_at".to_string());
+            }
+            Some(run_at) if run_at <= Utc::now() => {
+                return Err("run_at is in the past".to_string())
+            }
+            Some(_) => {}
+            None => {
+                TzSchedule::parse(&self.schedule, &self.timezone)?;
+            }
+        }
+
+        match (self.since, self.until) {
+            (Some(since), Some(until)) if since >= until => {
+                Err("since must be before until".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the tick of the cron manager at `tick` runs the job, always
+    /// for UTC and one-shot jobs.
+    pub fn is_due(&self, tick: DateTime<Utc>) -> bool {
+        match self.run_at {
+            Some(_) => true,
+            None => TzSchedule::parse(&self.schedule, &self.timezone)
+                .is_ok_and(|schedule| schedule.is_due(tick)),
+        }
+    }
+}
+
+/// The `escalonjobs.status` of a job; the node queries skip `done`,
+/// `failed` and `paused` ones. `paused` is only set through `/cron`, and
+/// `update_job` leaves paused rows alone whatever their manager reports.
+pub fn status(status: &EscalonJobStatus) -> String {
+    match status {
+        EscalonJobStatus::Scheduled => "scheduled",
+        EscalonJobStatus::Running => "running",
+        EscalonJobStatus::Done => "done",
+        EscalonJobStatus::Failed => "failed",
+    }
+    .to_string()
+}
+
+impl From<EJob> for NewEJob {
+    fn from(job: EJob) -> Self {
+        NewEJob {
+            schedule: job.schedule,
+            timezone: job.timezone,
+            run_at: job.run_at,
+            since: job.since,
+            until: job.until,
+        }
+    }
+}
+
+/// The manager ticks in UTC: zoned jobs on their carrier schedule, one-shot
+/// ones on an expression matching only their `run_at`.
+impl From<NewEJob> for NewEscalonJob {
+    fn from(job: NewEJob) -> Self {
+        let schedule = match (job.run_at, TzSchedule::parse(&job.schedule, &job.timezone)) {
+            (Some(run_at), _) => schedule::one_shot(run_at),
+            (None, Ok(schedule)) => schedule.carrier().to_string(),
+            (None, Err(_)) => job.schedule,
+        };
+
+        NewEscalonJob {
+            schedule,
+            since: job.since.map(|since| since.naive_utc()),
+            until: job.until.map(|until| until.naive_utc()),
+        }
+    }
+}
+
+/// Runs the cron job on each tick that is due for it. A recurring job stays
+/// scheduled when a tick runs out of retries, the failed attempts are in
+/// `cron_runs`; one-shot jobs end `done`, or `failed` until resumed.
+///
+/// A pause or resume through another node moves the job to a new row, so
+/// the copy left here finds none on its next tick and stops as `done`.
+#[rocket::async_trait]
+impl EscalonJobTrait<Context> for NewEJob {
+    async fn run_job(&self, mut job: EscalonJob, context: Context) -> EscalonJob {
+        if matches!(job.status, EscalonJobStatus::Failed) || !self.is_due(Utc::now()) {
+            return job;
+        }
+
+        // The job was deleted or replaced, maybe through another node
+        let cronjob = match repository::find_by_job(&context.db, job.job_id).await {
+            Ok(Some(cronjob)) => cronjob,
+            Ok(None) => {
+                info!("CRON: job {} no longer exists, stopping it", job.job_id);
+                job.status = EscalonJobStatus::Done;
+                return job;
+            }
+            Err(e) => {
+                error!("CRON: job {} could not be read; {e}", job.job_id);
+                return job;
+            }
+        };
+
+        // Rows paused in place, before pauses moved the job
+        match repository::get_status(&context.db, job.job_id).await {
+            Ok(Some(status)) if status == "paused" => {
+                info!("CRON: job {} was paused, stopping it", job.job_id);
+                job.status = EscalonJobStatus::Done;
+                return job;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("CRON: status of job {} could not be read; {e}", job.job_id);
+                return job;
+            }
+        }
+
+        job.status = match (
+            runner::run(&context, &cronjob, "schedule").await,
+            self.run_at,
+        ) {
+            (true, Some(_)) => EscalonJobStatus::Done,
+            (false, Some(_)) => EscalonJobStatus::Failed,
+            (true, None) => EscalonJobStatus::Scheduled,
+            (false, None) => {
+                warn!(
+                    "CRON: job {} ran out of retries, waiting for its next tick",
+                    job.job_id
+                );
+                EscalonJobStatus::Scheduled
+            }
+        };
+
+        job
+    }
+}
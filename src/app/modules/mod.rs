@@ -1,2 +1,8 @@
+mod admin;
 mod auth;
+mod bff;
+#[cfg(feature = "cron")]
+pub mod cron;
+#[cfg(feature = "cron")]
+pub mod escalon;
 pub mod routing;
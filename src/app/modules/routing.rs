@@ -1,7 +1,30 @@
+use super::admin::controller as admin_controller;
 use super::auth::controller as auth_controller;
+use super::bff::controller as bff_controller;
+#[cfg(feature = "cron")]
+use super::cron::controller as cron_controller;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::services::idempotency::Idempotency;
 
 pub fn router() -> rocket::fairing::AdHoc {
-    rocket::fairing::AdHoc::on_ignite("Routes", |rocket| async {
-        rocket.mount("/auth", auth_controller::routes())
+    rocket::fairing::AdHoc::try_on_ignite("Routes", |rocket| async {
+        let idempotency_ttl = match rocket.state::<SharedConfig>() {
+            Some(config) => config.get().idempotency_ttl,
+            None => {
+                error!("ERROR: routes; AuthConfig is not managed");
+                return Err(rocket);
+            }
+        };
+
+        let rocket = rocket
+            .manage(Idempotency::new(idempotency_ttl))
+            .mount("/auth", auth_controller::routes())
+            .mount("/admin", admin_controller::routes());
+
+        #[cfg(feature = "cron")]
+        let rocket = rocket.mount("/cron", cron_controller::routes());
+
+        bff_controller::mount(rocket).await
     })
 }
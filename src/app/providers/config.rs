@@ -0,0 +1,396 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use jsonwebtoken::Algorithm;
+use q_auth_client::keys::{Key, KeySource, DEFAULT_KID};
+use q_auth_client::{JwtKey, KeyRing, Verifier};
+use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
+use rocket::http::uri::Absolute;
+use serde::Serialize;
+
+use crate::app::providers::config_getter::ConfigGetter;
+use crate::app::providers::constants::{
+    ACCESS_TOKEN_EXPIRATION, REFRESH_TOKEN_EXPIRATION, ROBOT_TOKEN_EXPIRATION,
+};
+use crate::app::providers::cors::OriginPattern;
+use crate::app::providers::services::cookie::{CookiePolicy, CsrfMode};
+use crate::app::providers::services::idempotency;
+
+const REDACTED: &str = "[redacted]";
+const REQUIRED_URLS: [&str; 2] = ["profile", "user"];
+
+/// Application configuration, extracted and validated once at ignite.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub identity: String,
+    pub udp_port: u16,
+    pub secret_key: String,
+    pub keys: KeyRing,
+    /// Whether `/.well-known/jwks.json` is served; needs an EdDSA signing key.
+    pub serve_jwks: bool,
+    pub origins: Vec<OriginPattern>,
+    pub entity_urls: BTreeMap<&'static str, String>,
+    pub access_token_expiration: i64,
+    pub refresh_token_expiration: i64,
+    pub robot_token_expiration: i64,
+    /// Guest lifetime by project id, `guest_lifetime` for the rest; guests
+    /// of projects with neither never expire.
+    pub guest_lifetime: Option<i64>,
+    pub guest_lifetimes: BTreeMap<i32, i64>,
+    /// Seconds an `Idempotency-Key` is remembered. Read at ignite only.
+    pub idempotency_ttl: i64,
+    pub cookie: CookiePolicy,
+    pub clients: BTreeMap<String, ClientType>,
+}
+
+/// How a registered client receives its refresh token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum ClientType {
+    /// Private `HttpOnly` cookie, for the SPA.
+    Browser,
+    /// JSON body, for native apps; refreshed with `POST /auth/refresh`.
+    Native,
+}
+
+/// Managed handle to the current `AuthConfig`. Readers get a snapshot that
+/// stays consistent for the whole request, reloads swap it atomically.
+#[derive(Clone)]
+pub struct SharedConfig(Arc<RwLock<Arc<AuthConfig>>>);
+
+impl SharedConfig {
+    pub fn new(config: AuthConfig) -> Self {
+        SharedConfig(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    pub fn get(&self) -> Arc<AuthConfig> {
+        match self.0.read() {
+            Ok(config) => config.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Replaces the configuration and returns the previous one.
+    pub fn swap(&self, config: AuthConfig) -> Arc<AuthConfig> {
+        let mut current = match self.0.write() {
+            Ok(current) => current,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        std::mem::replace(&mut *current, Arc::new(config))
+    }
+}
+
+/// Lets the `q_auth_client` guards verify with the current key ring, so a
+/// reload rotates their keys too.
+#[rocket::async_trait]
+impl KeySource for SharedConfig {
+    async fn key(&self, kid: Option<&str>) -> Option<Key> {
+        self.get().keys.find(kid).cloned()
+    }
+}
+
+impl AuthConfig {
+    pub fn from_figment(figment: &Figment) -> Result<Self, Vec<String>> {
+        let raw = figment
+            .extract::<ConfigGetter>()
+            .map_err(|e| e.into_iter().map(|e| e.to_string()).collect::<Vec<_>>())?;
+
+        AuthConfig::try_from(raw)
+    }
+
+    pub fn fairing() -> AdHoc {
+        AdHoc::try_on_ignite("Load AuthConfig", |rocket| async {
+            let rocket = if lacks_debug_secret_key(rocket.figment()) {
+                warn!("CONFIG: no secret_key, using a random one until the next restart");
+                let key = random_secret_key();
+                let figment = rocket
+                    .figment()
+                    .clone()
+                    .merge((rocket::Config::SECRET_KEY, key));
+                rocket.configure(figment)
+            } else {
+                rocket
+            };
+
+            match AuthConfig::from_figment(rocket.figment()) {
+                Ok(config) => {
+                    let shared = SharedConfig::new(config);
+                    Ok(rocket.manage(Verifier::new(shared.clone())).manage(shared))
+                }
+                Err(errors) => {
+                    for e in errors {
+                        error!("ERROR: config; {e}");
+                    }
+                    Err(rocket)
+                }
+            }
+        })
+    }
+
+    pub fn entity_url(&self, entity: &str) -> Option<&str> {
+        self.entity_urls.get(entity).map(|url| url.as_str())
+    }
+
+    /// Static name and base url of a configured entity.
+    pub fn entity(&self, entity: &str) -> Option<(&'static str, &str)> {
+        self.entity_urls
+            .get_key_value(entity)
+            .map(|(name, url)| (*name, url.as_str()))
+    }
+
+    pub fn guest_lifetime(&self, project_id: i32) -> Option<i64> {
+        self.guest_lifetimes
+            .get(&project_id)
+            .copied()
+            .or(self.guest_lifetime)
+    }
+
+    pub fn redacted(&self) -> RedactedConfig {
+        RedactedConfig {
+            identity: self.identity.clone(),
+            udp_port: self.udp_port,
+            secret_key: REDACTED,
+            keys: self.keys.kids(),
+            clients: self.clients.clone(),
+            origins: self
+                .origins
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect(),
+            entity_urls: self.entity_urls.clone(),
+            access_token_expiration: self.access_token_expiration,
+            refresh_token_expiration: self.refresh_token_expiration,
+            robot_token_expiration: self.robot_token_expiration,
+            guest_lifetime: self.guest_lifetime,
+            guest_lifetimes: self.guest_lifetimes.clone(),
+            idempotency_ttl: self.idempotency_ttl,
+        }
+    }
+}
+
+/// Whether `figment` is the debug profile without a `secret_key`. Such
+/// builds get a random key at ignite, never one published with the code.
+pub fn lacks_debug_secret_key(figment: &Figment) -> bool {
+    figment.profile() == rocket::Config::DEBUG_PROFILE
+        && figment
+            .extract_inner::<String>(rocket::Config::SECRET_KEY)
+            .map_or(true, |key| key.trim().is_empty())
+}
+
+fn random_secret_key() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+impl TryFrom<ConfigGetter> for AuthConfig {
+    type Error = Vec<String>;
+
+    fn try_from(raw: ConfigGetter) -> Result<Self, Self::Error> {
+        let mut errors = Vec::new();
+
+        let secret_key = raw.secret_key.clone().unwrap_or_default();
+        if secret_key.trim().is_empty() {
+            errors.push("secret_key is mandatory".to_string());
+        }
+
+        let keys = match raw.jwt_keys.clone() {
+            Some(keys) => keys,
+            None => vec![JwtKey::hs256(DEFAULT_KID, &secret_key)],
+        };
+        let keys = KeyRing::new(&keys).map_err(|e| errors.extend(e)).ok();
+        if keys
+            .as_ref()
+            .is_some_and(|keys| keys.signing().encoding().is_none())
+        {
+            errors.push("jwt_keys first key signs, it needs a private key".to_string());
+        }
+
+        // Shared secrets are never published, so the services verifying with
+        // the jwks could not check a single token.
+        let serve_jwks = raw.serve_jwks.unwrap_or(false);
+        if serve_jwks
+            && keys
+                .as_ref()
+                .is_some_and(|keys| keys.signing().algorithm() != Algorithm::EdDSA)
+        {
+            errors.push("serve_jwks needs an EdDSA key first in jwt_keys".to_string());
+        }
+
+        let mut origins = Vec::new();
+        for origin in raw
+            .origin_url
+            .clone()
+            .unwrap_or_default()
+            .split(',')
+            .map(|origin| origin.trim())
+            .filter(|origin| !origin.is_empty())
+        {
+            match OriginPattern::parse(origin) {
+                Ok(pattern) => origins.push(pattern),
+                Err(e) => errors.push(format!("origin_url `{origin}`: {e}")),
+            }
+        }
+
+        let mut entity_urls = BTreeMap::new();
+        for (entity, url) in raw.entity_urls() {
+            match url {
+                Some(url) => match check_url(url) {
+                    Ok(()) if !url.ends_with('/') => {
+                        errors.push(format!("{entity}_url `{url}` must end with a slash"));
+                    }
+                    Ok(()) => {
+                        entity_urls.insert(entity, url.clone());
+                    }
+                    Err(e) => errors.push(format!("{entity}_url `{url}`: {e}")),
+                },
+                None if REQUIRED_URLS.contains(&entity) => {
+                    errors.push(format!("{entity}_url is mandatory"));
+                }
+                None => {}
+            }
+        }
+
+        let lifetime =
+            |name: &str, value: Option<i64>, default: i64, errors: &mut Vec<String>| {
+                let value = value.unwrap_or(default);
+                if value <= 0 {
+                    errors.push(format!("{name} must be a positive number of seconds"));
+                }
+                value
+            };
+
+        let access_token_expiration = lifetime(
+            "access_token_expiration",
+            raw.access_token_expiration,
+            ACCESS_TOKEN_EXPIRATION,
+            &mut errors,
+        );
+        let refresh_token_expiration = lifetime(
+            "refresh_token_expiration",
+            raw.refresh_token_expiration,
+            REFRESH_TOKEN_EXPIRATION,
+            &mut errors,
+        );
+        let robot_token_expiration = lifetime(
+            "robot_token_expiration",
+            raw.robot_token_expiration,
+            ROBOT_TOKEN_EXPIRATION,
+            &mut errors,
+        );
+
+        let guest_lifetime = raw
+            .guest_lifetime
+            .map(|value| lifetime("guest_lifetime", Some(value), value, &mut errors));
+
+        let mut guest_lifetimes = BTreeMap::new();
+        for (project_id, value) in raw.guest_lifetimes.clone().unwrap_or_default() {
+            let name = format!("guest_lifetimes.{project_id}");
+            match project_id.parse::<i32>() {
+                Ok(id) => {
+                    guest_lifetimes
+                        .insert(id, lifetime(&name, Some(value), value, &mut errors));
+                }
+                Err(_) => errors.push(format!("{name} must be keyed by a project id")),
+            }
+        }
+
+        let idempotency_ttl = lifetime(
+            "idempotency_ttl",
+            raw.idempotency_ttl,
+            idempotency::DEFAULT_TTL,
+            &mut errors,
+        );
+
+        if refresh_token_expiration < access_token_expiration {
+            errors.push(
+                "refresh_token_expiration must not be shorter than access_token_expiration"
+                    .to_string(),
+            );
+        }
+
+        let mut clients = BTreeMap::new();
+        for (client_id, kind) in raw.clients.clone().unwrap_or_default() {
+            match kind.to_lowercase().as_str() {
+                "browser" => {
+                    clients.insert(client_id, ClientType::Browser);
+                }
+                "native" => {
+                    clients.insert(client_id, ClientType::Native);
+                }
+                other => errors.push(format!(
+                    "clients.{client_id} `{other}` is not browser or native"
+                )),
+            }
+        }
+
+        let cookie = CookiePolicy::try_from(raw.refresh_cookie.clone().unwrap_or_default())
+            .map_err(|e| errors.extend(e))
+            .ok();
+        if cookie
+            .as_ref()
+            .is_some_and(|cookie| cookie.csrf == CsrfMode::Origin)
+            && origins.contains(&OriginPattern::Any)
+        {
+            errors.push(
+                "refresh_cookie.csrf origin cannot be used with a `*` in origin_url"
+                    .to_string(),
+            );
+        }
+
+        let (keys, cookie) = match (keys, cookie) {
+            (Some(keys), Some(cookie)) if errors.is_empty() => (keys, cookie),
+            _ => return Err(errors),
+        };
+
+        Ok(AuthConfig {
+            identity: ConfigGetter::get_identity(),
+            udp_port: raw.udp_port.unwrap_or(65056),
+            secret_key,
+            keys,
+            serve_jwks,
+            origins,
+            entity_urls,
+            access_token_expiration,
+            refresh_token_expiration,
+            robot_token_expiration,
+            guest_lifetime,
+            guest_lifetimes,
+            idempotency_ttl,
+            cookie,
+            clients,
+        })
+    }
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    let uri = Absolute::parse(url).map_err(|e| e.to_string())?;
+
+    match uri.scheme() {
+        "http" | "https" if uri.authority().is_some() => Ok(()),
+        "http" | "https" => Err("missing host".to_string()),
+        scheme => Err(format!("unsupported scheme `{scheme}`")),
+    }
+}
+
+/// `AuthConfig` as shown by `GET /admin/config`.
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RedactedConfig {
+    pub identity: String,
+    pub udp_port: u16,
+    pub secret_key: &'static str,
+    pub keys: Vec<String>,
+    pub clients: BTreeMap<String, ClientType>,
+    pub origins: Vec<String>,
+    pub entity_urls: BTreeMap<&'static str, String>,
+    pub access_token_expiration: i64,
+    pub refresh_token_expiration: i64,
+    pub robot_token_expiration: i64,
+    pub guest_lifetime: Option<i64>,
+    pub guest_lifetimes: BTreeMap<i32, i64>,
+    pub idempotency_ttl: i64,
+}
@@ -1,14 +1,21 @@
 #![allow(unused)]
 
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 
-#[derive(Deserialize)]
+use crate::app::providers::services::cookie::RefreshCookieConfig;
+use q_auth_client::JwtKey;
+
+#[derive(Clone, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct ConfigGetter {
     pub udp_port: Option<u16>,
     //
     pub origin_url: Option<String>,
     pub secret_key: Option<String>,
+    pub jwt_keys: Option<Vec<JwtKey>>,
+    pub serve_jwks: Option<bool>,
     //
     pub profile_url: Option<String>,
     pub user_url: Option<String>,
@@ -31,165 +38,47 @@ pub struct ConfigGetter {
     //
     pub project_url: Option<String>,
     pub cron_url: Option<String>,
+    //
+    pub access_token_expiration: Option<i64>,
+    pub refresh_token_expiration: Option<i64>,
+    pub robot_token_expiration: Option<i64>,
+    pub guest_lifetime: Option<i64>,
+    pub guest_lifetimes: Option<BTreeMap<String, i64>>,
+    pub idempotency_ttl: Option<i64>,
+    //
+    pub refresh_cookie: Option<RefreshCookieConfig>,
+    pub clients: Option<BTreeMap<String, String>>,
 }
 
 impl ConfigGetter {
-    pub fn get_entity_url(entity: &str) -> Option<String> {
-        match entity {
-            "profile" => ConfigGetter::get_profile_url(),
-            "user" => ConfigGetter::get_user_url(),
-            "auth" => ConfigGetter::get_auth_url(),
+    pub fn get_identity() -> String {
+        std::env::var("HOSTNAME").unwrap_or("server".to_string())
+    }
+
+    /// Configured base url of every entity, keyed by entity name.
+    pub fn entity_urls(&self) -> Vec<(&'static str, Option<&String>)> {
+        vec![
+            ("profile", self.profile_url.as_ref()),
+            ("user", self.user_url.as_ref()),
+            ("auth", self.auth_url.as_ref()),
             //
-            "message" => ConfigGetter::get_message_url(),
+            ("message", self.message_url.as_ref()),
             //
-            "question" => ConfigGetter::get_question_url(),
-            "answer" => ConfigGetter::get_answer_url(),
+            ("question", self.question_url.as_ref()),
+            ("answer", self.answer_url.as_ref()),
             //
-            "slide" => ConfigGetter::get_slide_url(),
-            "form" => ConfigGetter::get_form_url(),
-            "external" => ConfigGetter::get_external_url(),
+            ("slide", self.slide_url.as_ref()),
+            ("form", self.form_url.as_ref()),
+            ("external", self.external_url.as_ref()),
             //
-            "resource" => ConfigGetter::get_resource_url(),
-            "paper" => ConfigGetter::get_paper_url(),
+            ("resource", self.resource_url.as_ref()),
+            ("paper", self.paper_url.as_ref()),
             //
-            "logic" => ConfigGetter::get_logic_url(),
-            "checker" => ConfigGetter::get_checker_url(),
+            ("logic", self.logic_url.as_ref()),
+            ("checker", self.checker_url.as_ref()),
             //
-            "project" => ConfigGetter::get_project_url(),
-            "cron" => ConfigGetter::get_project_url(),
-            _ => None,
-        }
-    }
-
-    pub fn get_origin_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .origin_url
-    }
-
-    pub fn get_secret_key() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .secret_key
-    }
-
-    pub fn get_identity() -> String {
-        std::env::var("HOSTNAME").unwrap_or("server".to_string())
-    }
-
-    pub fn get_udp_port() -> Option<u16> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .udp_port
-    }
-}
-
-impl ConfigGetter {
-    fn get_profile_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .profile_url
-    }
-
-    fn get_user_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .user_url
-    }
-
-    fn get_auth_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .auth_url
-    }
-
-    fn get_message_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .message_url
-    }
-
-    fn get_question_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .question_url
-    }
-
-    fn get_answer_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .answer_url
-    }
-
-    fn get_slide_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .slide_url
-    }
-
-    fn get_form_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .form_url
-    }
-
-    fn get_external_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .external_url
-    }
-
-    fn get_resource_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .resource_url
-    }
-
-    fn get_paper_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .paper_url
-    }
-
-    fn get_logic_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .logic_url
-    }
-
-    fn get_checker_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .checker_url
-    }
-
-    fn get_project_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .project_url
-    }
-
-    fn get_cron_url() -> Option<String> {
-        rocket::Config::figment()
-            .extract::<ConfigGetter>()
-            .unwrap()
-            .cron_url
+            ("project", self.project_url.as_ref()),
+            ("cron", self.cron_url.as_ref()),
+        ]
     }
 }
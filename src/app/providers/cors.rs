@@ -1,8 +1,177 @@
-use rocket::fairing::{Fairing, Info, Kind};
-use rocket::http::Header;
-use rocket::{Request, Response};
+use std::collections::BTreeSet;
 
-use crate::app::providers::config_getter::ConfigGetter;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::uri::Absolute;
+use rocket::http::{Header, Method, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Build, Request, Response, Rocket};
+use serde::Deserialize;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::services::cookie::CsrfMode;
+
+/// Policy knobs read from the `cors` table of Rocket.toml. Allowed origins
+/// come from `origin_url` so they follow configuration reloads.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct CorsConfig {
+    pub allow_headers: Vec<String>,
+    pub expose_headers: Vec<String>,
+    pub max_age: u64,
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allow_headers: vec![
+                "Accept".to_string(),
+                "Authorization".to_string(),
+                "Content-Type".to_string(),
+                "Idempotency-Key".to_string(),
+                "X-CSRF-Token".to_string(),
+            ],
+            expose_headers: vec!["WWW-Authenticate".to_string()],
+            max_age: 600,
+            allow_credentials: true,
+        }
+    }
+}
+
+/// One entry of `origin_url`: `*`, an exact origin such as
+/// `https://app.example.com`, or every subdomain of a domain with
+/// `https://*.example.com`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginPattern {
+    Any,
+    Exact(String),
+    Subdomain { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    pub fn parse(origin: &str) -> Result<Self, String> {
+        let origin = origin.trim().trim_end_matches('/').to_lowercase();
+        if origin == "*" {
+            return Ok(OriginPattern::Any);
+        }
+
+        let (scheme, host) = origin
+            .split_once("://")
+            .ok_or_else(|| "missing scheme".to_string())?;
+
+        let (wildcard, checked) = match host.strip_prefix("*.") {
+            Some(domain) => (true, format!("{scheme}://wildcard.{domain}")),
+            None => (false, origin.clone()),
+        };
+
+        let uri = Absolute::parse(&checked).map_err(|e| e.to_string())?;
+        match uri.scheme() {
+            "http" | "https" => {}
+            scheme => return Err(format!("unsupported scheme `{scheme}`")),
+        }
+        if uri.authority().is_none() {
+            return Err("missing host".to_string());
+        }
+        if !uri.path().is_empty() || uri.query().is_some() {
+            return Err("an origin has no path".to_string());
+        }
+
+        match wildcard {
+            true => Ok(OriginPattern::Subdomain {
+                scheme: format!("{scheme}://"),
+                suffix: host[1..].to_string(),
+            }),
+            false => Ok(OriginPattern::Exact(origin)),
+        }
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Any => true,
+            OriginPattern::Exact(exact) => exact.eq_ignore_ascii_case(origin),
+            OriginPattern::Subdomain { scheme, suffix } => {
+                let origin = origin.to_lowercase();
+                match origin
+                    .strip_prefix(scheme.as_str())
+                    .and_then(|host| host.strip_suffix(suffix.as_str()))
+                {
+                    Some(label) => {
+                        !label.is_empty()
+                            && label
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for OriginPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OriginPattern::Any => write!(f, "*"),
+            OriginPattern::Exact(origin) => write!(f, "{origin}"),
+            OriginPattern::Subdomain { scheme, suffix } => write!(f, "{scheme}*{suffix}"),
+        }
+    }
+}
+
+/// Methods the mounted routes accept for the request path, OPTIONS aside.
+struct AllowedMethods(BTreeSet<&'static str>);
+
+fn allowed_methods<'r>(request: &'r Request<'_>) -> &'r BTreeSet<&'static str> {
+    &request
+        .local_cache(|| {
+            let path = request.uri().path();
+            AllowedMethods(
+                request
+                    .rocket()
+                    .routes()
+                    .filter(|route| route.method != Method::Options)
+                    .filter(|route| path_matches(route.uri.path(), path.as_str()))
+                    .map(|route| route.method.as_str())
+                    .collect(),
+            )
+        })
+        .0
+}
+
+fn path_matches(route: &str, path: &str) -> bool {
+    let mut route = route.split('/').filter(|s| !s.is_empty());
+    let mut path = path.split('/').filter(|s| !s.is_empty());
+
+    loop {
+        match (route.next(), path.next()) {
+            (Some(r), _) if r.starts_with('<') && r.ends_with("..>") => return true,
+            (Some(r), Some(_)) if r.starts_with('<') && r.ends_with('>') => {}
+            (Some(r), Some(s)) if r == s => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Request guard that only lets preflights for known paths through.
+struct KnownPath;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for KnownPath {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match allowed_methods(request).is_empty() {
+            true => Outcome::Error((Status::NotFound, ())),
+            false => Outcome::Success(KnownPath),
+        }
+    }
+}
+
+#[options("/<_..>")]
+fn preflight(_path: KnownPath) -> Status {
+    Status::NoContent
+}
 
 pub struct Cors;
 
@@ -10,34 +179,106 @@ pub struct Cors;
 impl Fairing for Cors {
     fn info(&self) -> Info {
         Info {
-            name: "Add CORS headers to response",
-            kind: Kind::Response,
+            name: "CORS policy",
+            kind: Kind::Ignite | Kind::Response,
         }
     }
 
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let config = match rocket.figment().extract_inner::<CorsConfig>("cors") {
+            Ok(config) => config,
+            Err(e) if e.missing() => CorsConfig::default(),
+            Err(e) => {
+                error!("ERROR: cors; invalid cors config; {e}");
+                return Err(rocket);
+            }
+        };
+
+        Ok(rocket.manage(config).mount("/", routes![preflight]))
+    }
+
     async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
-        let c_origin = request.headers().get_one("Origin");
-        let origins = ConfigGetter::get_origin_url()
-            .unwrap_or("*".to_string())
-            .to_string();
-
-        let allowed_origins = if origins.contains(',') {
-            origins.split(',').collect::<Vec<&str>>()
-        } else {
-            vec![&*origins]
+        let (config, shared) = match (
+            request.rocket().state::<CorsConfig>(),
+            request.rocket().state::<SharedConfig>(),
+        ) {
+            (Some(config), Some(shared)) => (config, shared.get()),
+            _ => return,
+        };
+
+        // Caches must key on Origin even for requests that sent none, or a
+        // response without CORS headers could be served to a browser origin.
+        response.adjoin_header(Header::new("Vary", "Origin"));
+
+        let origin = match request.headers().get_one("Origin") {
+            Some(origin) => origin,
+            None => return,
         };
 
-        let origin = match c_origin {
-            Some(claimed_origin) if allowed_origins.contains(&claimed_origin) => claimed_origin,
-            _ => "*",
+        let pattern = match shared
+            .origins
+            .iter()
+            .find(|pattern| pattern.matches(origin))
+        {
+            Some(pattern) => pattern,
+            None => return,
         };
 
-        response.set_header(Header::new("Access-Control-Allow-Origin", origin));
-        response.set_header(Header::new(
-            "Access-Control-Allow-Methods",
-            "POST, GET, PUT, PATCH, OPTIONS",
-        ));
-        response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
-        response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+        // Credentials are never allowed together with `*`.
+        match pattern {
+            OriginPattern::Any => {
+                response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+            }
+            _ => {
+                response.set_header(Header::new(
+                    "Access-Control-Allow-Origin",
+                    origin.to_string(),
+                ));
+                if config.allow_credentials {
+                    response
+                        .set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+                }
+            }
+        }
+
+        let is_preflight = request.method() == Method::Options
+            && request.headers().contains("Access-Control-Request-Method");
+
+        if is_preflight {
+            let methods = allowed_methods(request);
+            if methods.is_empty() {
+                return;
+            }
+
+            let methods = methods
+                .iter()
+                .copied()
+                .chain(["OPTIONS"])
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            response.set_header(Header::new("Access-Control-Allow-Methods", methods));
+            // A renamed double submit header has to be allowed as well.
+            let mut headers = config.allow_headers.clone();
+            if let CsrfMode::DoubleSubmit { header, .. } = &shared.cookie.csrf {
+                if !headers.iter().any(|h| h.eq_ignore_ascii_case(header)) {
+                    headers.push(header.clone());
+                }
+            }
+
+            response.set_header(Header::new(
+                "Access-Control-Allow-Headers",
+                headers.join(", "),
+            ));
+            response.set_header(Header::new(
+                "Access-Control-Max-Age",
+                config.max_age.to_string(),
+            ));
+        } else if !config.expose_headers.is_empty() {
+            response.set_header(Header::new(
+                "Access-Control-Expose-Headers",
+                config.expose_headers.join(", "),
+            ));
+        }
     }
 }
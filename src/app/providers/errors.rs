@@ -0,0 +1,296 @@
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder, Response};
+use rocket::serde::json::Json;
+use rocket::{Catcher, Request};
+use serde::Serialize;
+
+use crate::app::providers::services::claims::ClaimsError;
+use crate::app::providers::services::upstream::{UpstreamError, UpstreamErrorKind};
+
+const REALM: &str = "q-api";
+
+#[derive(Debug, Clone)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    ExpiredToken,
+    MissingRefreshToken,
+    MissingSession,
+    InvalidCredentials,
+    Forbidden,
+    CsrfRejected,
+    InvalidClient,
+    UnauthorizedClient,
+    NotAMember,
+    Inactive,
+    GuestExpired,
+    IdempotencyInFlight,
+    IdempotencyKeyReused,
+    InvalidRequest(String),
+    UserNotFound,
+    Upstream(UpstreamError),
+    TokenEncoding,
+    Http(Status),
+}
+
+/// RFC 7807 body sent as `application/problem+json`.
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+}
+
+impl AuthError {
+    pub fn status(&self) -> Status {
+        match self {
+            AuthError::MissingToken
+            | AuthError::InvalidToken
+            | AuthError::ExpiredToken
+            | AuthError::MissingRefreshToken
+            | AuthError::MissingSession
+            | AuthError::InvalidCredentials
+            | AuthError::InvalidClient => Status::Unauthorized,
+            AuthError::Forbidden
+            | AuthError::CsrfRejected
+            | AuthError::UnauthorizedClient
+            | AuthError::NotAMember
+            | AuthError::Inactive
+            | AuthError::GuestExpired => Status::Forbidden,
+            AuthError::IdempotencyInFlight | AuthError::IdempotencyKeyReused => {
+                Status::Conflict
+            }
+            AuthError::InvalidRequest(_) => Status::BadRequest,
+            AuthError::UserNotFound => Status::NotFound,
+            AuthError::Upstream(error) => match error.kind {
+                UpstreamErrorKind::Timeout => Status::GatewayTimeout,
+                UpstreamErrorKind::Connect => Status::ServiceUnavailable,
+                UpstreamErrorKind::NotConfigured => Status::InternalServerError,
+                _ => Status::BadGateway,
+            },
+            AuthError::TokenEncoding => Status::InternalServerError,
+            AuthError::Http(status) => *status,
+        }
+    }
+
+    /// Stable, machine readable identifier of the error.
+    pub fn code(&self) -> String {
+        match self {
+            AuthError::MissingToken => "missing_token".to_string(),
+            AuthError::InvalidToken => "invalid_token".to_string(),
+            AuthError::ExpiredToken => "token_expired".to_string(),
+            AuthError::MissingRefreshToken => "refresh_token_missing".to_string(),
+            AuthError::MissingSession => "session_missing".to_string(),
+            AuthError::InvalidCredentials => "invalid_credentials".to_string(),
+            AuthError::Forbidden => "insufficient_scope".to_string(),
+            AuthError::CsrfRejected => "csrf_rejected".to_string(),
+            AuthError::InvalidClient => "invalid_client".to_string(),
+            AuthError::UnauthorizedClient => "unauthorized_client".to_string(),
+            AuthError::NotAMember => "project_membership_missing".to_string(),
+            AuthError::Inactive => "account_inactive".to_string(),
+            AuthError::GuestExpired => "guest_expired".to_string(),
+            AuthError::IdempotencyInFlight => "idempotency_key_in_use".to_string(),
+            AuthError::IdempotencyKeyReused => "idempotency_key_reused".to_string(),
+            AuthError::InvalidRequest(_) => "invalid_request".to_string(),
+            AuthError::UserNotFound => "user_not_found".to_string(),
+            AuthError::Upstream(error) => match error.kind {
+                UpstreamErrorKind::Timeout => "upstream_timeout".to_string(),
+                UpstreamErrorKind::Connect => "upstream_unavailable".to_string(),
+                UpstreamErrorKind::Decode => "upstream_invalid_response".to_string(),
+                UpstreamErrorKind::NotConfigured => "upstream_not_configured".to_string(),
+                _ => "upstream_error".to_string(),
+            },
+            AuthError::TokenEncoding => "token_encoding_failed".to_string(),
+            AuthError::Http(status) => status
+                .reason_lossy()
+                .to_lowercase()
+                .replace(['-', ' '], "_")
+                .replace('\'', ""),
+        }
+    }
+
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            AuthError::MissingToken => Some("No bearer token was provided".to_string()),
+            AuthError::InvalidToken => {
+                Some("The token is malformed or its signature is invalid".to_string())
+            }
+            AuthError::ExpiredToken => Some("The token has expired".to_string()),
+            AuthError::MissingRefreshToken => Some("The refresh cookie is missing".to_string()),
+            AuthError::MissingSession => {
+                Some("There is no active session, log in again".to_string())
+            }
+            AuthError::InvalidCredentials => {
+                Some("The profile service rejected the token".to_string())
+            }
+            AuthError::Forbidden => {
+                Some("The token does not grant access to this resource".to_string())
+            }
+            AuthError::CsrfRejected => {
+                Some("The request did not pass the CSRF check".to_string())
+            }
+            AuthError::InvalidClient => Some("The client id is not registered".to_string()),
+            AuthError::UnauthorizedClient => {
+                Some("This client type may not use this refresh mode".to_string())
+            }
+            AuthError::NotAMember => {
+                Some("The user does not belong to this project".to_string())
+            }
+            AuthError::Inactive => {
+                Some("The user or its project membership has been deactivated".to_string())
+            }
+            AuthError::GuestExpired => Some("The guest account has expired".to_string()),
+            AuthError::IdempotencyInFlight => {
+                Some("A request with this Idempotency-Key is still running".to_string())
+            }
+            AuthError::IdempotencyKeyReused => {
+                Some("This Idempotency-Key was already used with another body".to_string())
+            }
+            AuthError::InvalidRequest(detail) => Some(detail.clone()),
+            AuthError::UserNotFound => {
+                Some("The user service does not know this user".to_string())
+            }
+            AuthError::Upstream(error) => Some(match error.kind {
+                UpstreamErrorKind::Timeout => {
+                    format!("The {} service timed out", error.service)
+                }
+                UpstreamErrorKind::Connect | UpstreamErrorKind::Request => {
+                    format!("The {} service could not be reached", error.service)
+                }
+                UpstreamErrorKind::Status(status) => {
+                    format!(
+                        "The {} service answered with status {status}",
+                        error.service
+                    )
+                }
+                UpstreamErrorKind::Decode => {
+                    format!("The {} service sent an unexpected response", error.service)
+                }
+                UpstreamErrorKind::NotConfigured => {
+                    format!("The {} service url is not configured", error.service)
+                }
+            }),
+            AuthError::TokenEncoding => Some("The tokens could not be generated".to_string()),
+            AuthError::Http(_) => None,
+        }
+    }
+
+    /// RFC 6750 challenge for the `WWW-Authenticate` header.
+    pub fn challenge(&self) -> Option<String> {
+        match self {
+            AuthError::MissingToken | AuthError::MissingRefreshToken => {
+                Some(format!("Bearer realm=\"{REALM}\""))
+            }
+            AuthError::InvalidToken | AuthError::ExpiredToken => Some(format!(
+                "Bearer realm=\"{REALM}\", error=\"invalid_token\", error_description=\"{}\"",
+                self.detail().unwrap_or_default()
+            )),
+            AuthError::InvalidCredentials => Some(format!("Bearer realm=\"{REALM}\"")),
+            AuthError::Forbidden => Some(format!(
+                "Bearer realm=\"{REALM}\", error=\"insufficient_scope\""
+            )),
+            AuthError::Http(status) if *status == Status::Unauthorized => {
+                Some(format!("Bearer realm=\"{REALM}\""))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_problem(&self, instance: Option<String>) -> Problem {
+        let status = self.status();
+
+        Problem {
+            kind: format!("urn:q-api:auth:{}", self.code()),
+            title: status.reason_lossy().to_string(),
+            status: status.code,
+            code: self.code(),
+            detail: self.detail(),
+            instance,
+            service: match self {
+                AuthError::Upstream(error) => Some(error.service.to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Keeps the error in the request cache so the catcher can render it
+    /// after a request guard fails.
+    pub fn stash(&self, request: &Request<'_>) {
+        request.local_cache(|| Stashed(Some(self.clone())));
+    }
+}
+
+impl From<ClaimsError> for AuthError {
+    fn from(error: ClaimsError) -> Self {
+        match error {
+            ClaimsError::MissingToken => AuthError::MissingToken,
+            ClaimsError::InvalidToken => AuthError::InvalidToken,
+            ClaimsError::ExpiredToken => AuthError::ExpiredToken,
+            ClaimsError::InsufficientRole => AuthError::Forbidden,
+        }
+    }
+}
+
+impl From<UpstreamError> for AuthError {
+    fn from(error: UpstreamError) -> Self {
+        warn!("AUTH: {error}");
+        AuthError::Upstream(error)
+    }
+}
+
+impl From<Status> for AuthError {
+    fn from(status: Status) -> Self {
+        AuthError::Http(status)
+    }
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code(), self.detail().unwrap_or_default())
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl<'r> Responder<'r, 'static> for AuthError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let problem = self.to_problem(Some(request.uri().path().to_string()));
+
+        let mut response = Response::build_from(Json(problem).respond_to(request)?)
+            .status(self.status())
+            .header(ContentType::new("application", "problem+json"))
+            .finalize();
+
+        if let Some(challenge) = self.challenge() {
+            response.set_header(Header::new("WWW-Authenticate", challenge));
+        }
+
+        Ok(response)
+    }
+}
+
+struct Stashed(Option<AuthError>);
+
+pub fn catchers() -> Vec<Catcher> {
+    catchers![default_catcher]
+}
+
+#[catch(default)]
+fn default_catcher(status: Status, request: &Request<'_>) -> AuthError {
+    match &request.local_cache(|| Stashed(None)).0 {
+        Some(error) => error.clone(),
+        None => match q_auth_client::guards::failure(request) {
+            Some(error) => AuthError::from(error),
+            None => AuthError::from(status),
+        },
+    }
+}
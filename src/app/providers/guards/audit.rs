@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::json;
+
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::audit::{AuditLog, EventKind, NewAuthEvent, SUCCESS};
+use crate::app::providers::services::outbox::{Effect, Outbox};
+
+/// Longest `X-Client-Id` recorded, the size of the `auth_events.client`
+/// column. The header is not checked against the registered clients here.
+const CLIENT_MAX_LEN: usize = 64;
+
+/// Audit log plus what the request tells about where it came from. Events
+/// are also exported to the `audit` outbox sink when it is configured.
+///
+/// With a database the event and the effects of the state change it records
+/// are written in one transaction.
+pub struct Audit {
+    log: Arc<dyn AuditLog>,
+    outbox: Option<Outbox>,
+    #[cfg(feature = "db_sqlx")]
+    db: Option<rocket_db_pools::sqlx::PgPool>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    client: Option<String>,
+}
+
+impl Audit {
+    /// Appends the event; a failing store is logged, never the request's
+    /// problem.
+    pub async fn record(
+        &self,
+        kind: EventKind,
+        actor_id: Option<i32>,
+        subject_id: Option<i32>,
+        outcome: Result<(), &AuthError>,
+    ) {
+        self.record_with(kind, actor_id, subject_id, outcome, Vec::new())
+            .await;
+    }
+
+    /// `record` plus the `effects` of the state change, enqueued in the
+    /// outbox along with the event.
+    pub async fn record_with(
+        &self,
+        kind: EventKind,
+        actor_id: Option<i32>,
+        subject_id: Option<i32>,
+        outcome: Result<(), &AuthError>,
+        mut effects: Vec<Effect>,
+    ) {
+        let event = NewAuthEvent {
+            kind,
+            outcome: match outcome {
+                Ok(()) => SUCCESS.to_string(),
+                Err(e) => e.code(),
+            },
+            actor_id,
+            subject_id,
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            client: self.client.clone(),
+        };
+
+        if let Some(outbox) = &self.outbox {
+            let body = json!({ "occurred_at": chrono::Utc::now(), "event": &event });
+            effects.extend(outbox.publication("audit", body));
+        }
+
+        if let Err(e) = self.write(event, effects).await {
+            error!("AUDIT: {} event could not be recorded; {e}", kind.as_str());
+        }
+    }
+
+    async fn write(&self, event: NewAuthEvent, effects: Vec<Effect>) -> Result<(), String> {
+        let outbox = match &self.outbox {
+            Some(outbox) => outbox,
+            None if effects.is_empty() => return self.log.append(event).await,
+            None => {
+                error!("AUDIT: Outbox is not managed, {effects:?} are lost");
+                return self.log.append(event).await;
+            }
+        };
+
+        #[cfg(feature = "db_sqlx")]
+        if let Some(db) = &self.db {
+            let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+            self.log.append_in(&mut tx, event).await?;
+            if !effects.is_empty() {
+                outbox.store().enqueue_in(&mut tx, effects).await?;
+            }
+
+            return tx.commit().await.map_err(|e| e.to_string());
+        }
+
+        if !effects.is_empty() {
+            outbox.send(effects).await;
+        }
+
+        self.log.append(event).await
+    }
+
+    /// Records `result` only if it failed, for the checks made before the
+    /// event itself, and hands it back.
+    pub async fn rejected<T>(
+        &self,
+        kind: EventKind,
+        result: Result<T, AuthError>,
+    ) -> Result<T, AuthError> {
+        if let Err(e) = &result {
+            self.record(kind, None, None, Err(e)).await;
+        }
+
+        result
+    }
+
+    /// Records the outcome of `result` and hands it back.
+    pub async fn track<T>(
+        &self,
+        kind: EventKind,
+        actor_id: Option<i32>,
+        subject_id: Option<i32>,
+        result: Result<T, AuthError>,
+    ) -> Result<T, AuthError> {
+        self.record(kind, actor_id, subject_id, result.as_ref().map(|_| ()))
+            .await;
+
+        result
+    }
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for Audit {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let log = match request.rocket().state::<Arc<dyn AuditLog>>() {
+            Some(log) => log.clone(),
+            None => {
+                error!("AUDIT: AuditLog is not managed");
+                let error = AuthError::Http(rocket::http::Status::InternalServerError);
+                error.stash(request);
+                return Outcome::Error((error.status(), error));
+            }
+        };
+
+        let header = |name| {
+            request
+                .headers()
+                .get_one(name)
+                .map(|value| value.to_string())
+        };
+
+        Outcome::Success(Audit {
+            log,
+            outbox: request.rocket().state::<Outbox>().cloned(),
+            #[cfg(feature = "db_sqlx")]
+            db: {
+                use rocket_db_pools::Database;
+                crate::database::connection::Db::fetch(request.rocket()).map(|db| db.0.clone())
+            },
+            ip: request.client_ip().map(|ip| ip.to_string()),
+            user_agent: header("User-Agent"),
+            client: header("X-Client-Id")
+                .map(|client| client.chars().take(CLIENT_MAX_LEN).collect()),
+        })
+    }
+}
@@ -1,62 +1,90 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
 use rocket::request::{FromRequest, Outcome, Request};
 
-use crate::app::providers::services::claims::{Claims, ClaimsError};
-use crate::app::providers::services::token::Token;
+use crate::app::providers::config::{AuthConfig, SharedConfig};
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::claims::Claims;
+use crate::app::providers::services::revocation::Revocations;
+use crate::app::providers::services::token::{self, Token};
+
+pub use q_auth_client::guards::{
+    AccessClaims, AdminClaims, GuestClaims, OperatorClaims, ParticipantClaims,
+    ResearcherClaims, RobotClaims,
+};
 
-pub struct AccessClaims(pub Claims);
 pub struct RefreshClaims(pub Claims);
 
-#[async_trait]
-impl<'r> FromRequest<'r> for RefreshClaims {
-    type Error = ClaimsError;
+fn fail<S>(request: &Request<'_>, error: AuthError) -> Outcome<S, AuthError> {
+    error.stash(request);
 
-    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let token: Token = match Token::from_cookie(request) {
-            Some(token) => token,
-            None => {
-                return Outcome::Error((
-                    rocket::http::Status::BadRequest,
-                    ClaimsError::MissingToken,
-                ));
-            }
-        };
+    Outcome::Error((error.status(), error))
+}
 
-        let claims = match token.decode() {
-            Ok(claims) => claims.claims,
-            Err(e) => {
-                println!("Error: {:?}", e);
-                return Outcome::Error((
-                    rocket::http::Status::Unauthorized,
-                    ClaimsError::InvalidToken,
-                ));
-            }
-        };
+fn config(request: &Request<'_>) -> Result<Arc<AuthConfig>, AuthError> {
+    match request.rocket().state::<SharedConfig>() {
+        Some(config) => Ok(config.get()),
+        None => {
+            error!("AUTH: AuthConfig is not managed");
+            Err(AuthError::Http(rocket::http::Status::InternalServerError))
+        }
+    }
+}
+
+impl RefreshClaims {
+    /// Validation shared by the cookie and the body refresh flows. Only
+    /// refresh tokens carry the `user_token`.
+    pub async fn from_token(
+        config: &AuthConfig,
+        revocations: &Revocations,
+        token: Token,
+    ) -> Result<Self, AuthError> {
+        let claims = token::decode(&token, config).map_err(AuthError::from)?;
 
-        Outcome::Success(RefreshClaims(claims))
+        if claims.user.user_token.is_none() {
+            return Err(AuthError::InvalidToken);
+        }
+
+        match revocations.revokes(&claims).await? {
+            true => Err(AuthError::Inactive),
+            false => Ok(RefreshClaims(claims)),
+        }
     }
 }
 
+/// Fails with the `AuthError` itself, so handlers taking a
+/// `Result<RefreshClaims, AuthError>` can audit rejected refreshes.
 #[async_trait]
-impl<'r> FromRequest<'r> for AccessClaims {
-    type Error = ClaimsError;
+impl<'r> FromRequest<'r> for RefreshClaims {
+    type Error = AuthError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let token = match Token::from_header(request) {
+        let config = match config(request) {
+            Ok(config) => config,
+            Err(error) => return fail(request, error),
+        };
+
+        let token: Token = match token::from_cookie(request, &config.cookie) {
             Some(token) => token,
-            None => return Outcome::Forward(rocket::http::Status::Ok), // ???
+            None => return fail(request, AuthError::MissingRefreshToken),
         };
 
-        let claims = match token.decode() {
-            Ok(claims) => claims.claims,
-            Err(e) => {
-                println!("Error: {:?}", e);
-                return Outcome::Error((
-                    rocket::http::Status::Unauthorized,
-                    ClaimsError::InvalidToken,
-                ));
+        let revocations = match request.rocket().state::<Revocations>() {
+            Some(revocations) => revocations,
+            None => {
+                error!("AUTH: Revocations are not managed");
+                return fail(
+                    request,
+                    AuthError::Http(rocket::http::Status::InternalServerError),
+                );
             }
         };
 
-        Outcome::Success(AccessClaims(claims))
+        match RefreshClaims::from_token(&config, revocations, token).await {
+            Ok(claims) => Outcome::Success(claims),
+            Err(error) => fail(request, error),
+        }
     }
 }
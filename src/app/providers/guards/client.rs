@@ -0,0 +1,46 @@
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::app::providers::config::{ClientType, SharedConfig};
+use crate::app::providers::errors::AuthError;
+
+const CLIENT_HEADER: &str = "X-Client-Id";
+
+/// Client named by the `X-Client-Id` header. Requests without it are taken
+/// as coming from the browser SPA.
+pub struct RegisteredClient {
+    pub id: Option<String>,
+    pub kind: ClientType,
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for RegisteredClient {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let client_id = match request.headers().get_one(CLIENT_HEADER) {
+            Some(client_id) => client_id,
+            None => {
+                return Outcome::Success(RegisteredClient {
+                    id: None,
+                    kind: ClientType::Browser,
+                })
+            }
+        };
+
+        let kind = request
+            .rocket()
+            .state::<SharedConfig>()
+            .and_then(|config| config.get().clients.get(client_id).copied());
+
+        match kind {
+            Some(kind) => Outcome::Success(RegisteredClient {
+                id: Some(client_id.to_string()),
+                kind,
+            }),
+            None => {
+                AuthError::InvalidClient.stash(request);
+                Outcome::Error((AuthError::InvalidClient.status(), AuthError::InvalidClient))
+            }
+        }
+    }
+}
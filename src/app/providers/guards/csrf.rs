@@ -0,0 +1,86 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::cors::OriginPattern;
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::cookie::CsrfMode;
+
+/// Guards the endpoints authenticated by the refresh cookie alone. Place it
+/// before `RefreshClaims`, which consumes the cookie.
+pub struct CsrfChecked;
+
+fn fail(request: &Request<'_>) -> Outcome<CsrfChecked, AuthError> {
+    AuthError::CsrfRejected.stash(request);
+
+    Outcome::Error((Status::Forbidden, AuthError::CsrfRejected))
+}
+
+/// `scheme://host[:port]` of a `Referer` url.
+fn referer_origin(referer: &str) -> Option<String> {
+    let mut parts = referer.splitn(4, '/');
+    let scheme = parts.next()?;
+    let _ = parts.next()?;
+    let host = parts.next().filter(|host| !host.is_empty())?;
+
+    Some(format!("{scheme}//{host}"))
+}
+
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for CsrfChecked {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let config = match request.rocket().state::<SharedConfig>() {
+            Some(config) => config.get(),
+            None => return fail(request),
+        };
+
+        let checked = match &config.cookie.csrf {
+            CsrfMode::Off => true,
+            CsrfMode::Origin => {
+                let origin = match request.headers().get_one("Origin") {
+                    Some(origin) => Some(origin.to_string()),
+                    None => request
+                        .headers()
+                        .get_one("Referer")
+                        .and_then(referer_origin),
+                };
+
+                match origin {
+                    // `*` is rejected with this mode at load, never trust it here.
+                    Some(origin) => config
+                        .origins
+                        .iter()
+                        .filter(|pattern| **pattern != OriginPattern::Any)
+                        .any(|pattern| pattern.matches(&origin)),
+                    None => false,
+                }
+            }
+            CsrfMode::DoubleSubmit { cookie, header } => {
+                match (
+                    request.cookies().get(cookie),
+                    request.headers().get_one(header),
+                ) {
+                    (Some(cookie), Some(header)) if !header.is_empty() => {
+                        same_token(cookie.value(), header)
+                    }
+                    _ => false,
+                }
+            }
+        };
+
+        match checked {
+            true => Outcome::Success(CsrfChecked),
+            false => fail(request),
+        }
+    }
+}
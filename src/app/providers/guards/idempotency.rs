@@ -0,0 +1,123 @@
+use std::future::Future;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::serde_json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::idempotency::{Claim, Idempotency};
+
+const HEADER: &str = "Idempotency-Key";
+const MAX_KEY_LEN: usize = 255;
+
+/// The `Idempotency-Key` of the request, scoped to the client and route.
+/// Requests without the header run every time.
+pub struct IdempotencyKey {
+    store: Idempotency,
+    key: Option<String>,
+}
+
+/// What `IdempotencyKey::once` did with the request.
+pub enum Once<T> {
+    Ran(T),
+    /// The outcome of the first request with the key.
+    Replayed(T),
+}
+
+impl<T> Once<T> {
+    pub fn replayed(&self) -> bool {
+        matches!(self, Once::Replayed(_))
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            Once::Ran(value) | Once::Replayed(value) => value,
+        }
+    }
+}
+
+/// Frees a key whose request did not complete, even if it never finishes.
+struct Pending<'a>(&'a Idempotency, &'a str);
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.release(self.1);
+    }
+}
+
+impl IdempotencyKey {
+    /// Runs `request` once per key; repeats with the same `body` get the
+    /// first outcome back, with another body they are rejected.
+    pub async fn once<T, F>(&self, body: &[u8], request: F) -> Result<Once<T>, AuthError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T, AuthError>>,
+    {
+        let key = match &self.key {
+            Some(key) => key,
+            None => return request.await.map(Once::Ran),
+        };
+
+        let fingerprint = Idempotency::fingerprint(&[key.as_bytes(), body]);
+        if let Claim::Replay(outcome) = self.store.claim(key, &fingerprint)? {
+            info!("IDEMPOTENCY: replaying {key}");
+            return serde_json::from_str(&outcome)
+                .map(Once::Replayed)
+                .map_err(|e| {
+                    error!("IDEMPOTENCY: stored outcome of {key} is unreadable; {e}");
+                    AuthError::Http(Status::InternalServerError)
+                });
+        }
+
+        let _pending = Pending(&self.store, key);
+        let result = request.await;
+
+        if let Ok(value) = &result {
+            match serde_json::to_string(value) {
+                Ok(outcome) => self.store.complete(key, outcome),
+                Err(e) => error!("IDEMPOTENCY: outcome of {key} not stored; {e}"),
+            }
+        }
+
+        result.map(Once::Ran)
+    }
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let store = match request.rocket().state::<Idempotency>() {
+            Some(store) => store.clone(),
+            None => {
+                error!("IDEMPOTENCY: Idempotency is not managed");
+                let error = AuthError::Http(Status::InternalServerError);
+                error.stash(request);
+                return Outcome::Error((error.status(), error));
+            }
+        };
+
+        let key = match request.headers().get_one(HEADER) {
+            Some(key) if key.is_empty() || key.len() > MAX_KEY_LEN => {
+                let error = AuthError::InvalidRequest(format!(
+                    "{HEADER} must have between 1 and {MAX_KEY_LEN} characters"
+                ));
+                error.stash(request);
+                return Outcome::Error((error.status(), error));
+            }
+            Some(key) => {
+                let client = request
+                    .headers()
+                    .get_one("X-Client-Id")
+                    .unwrap_or("browser");
+                Some(format!("{client} {} {key}", request.uri().path()))
+            }
+            None => None,
+        };
+
+        Outcome::Success(IdempotencyKey { store, key })
+    }
+}
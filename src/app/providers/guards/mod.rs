@@ -1 +1,10 @@
+pub mod audit;
 pub mod claims;
+pub mod client;
+pub mod csrf;
+pub mod idempotency;
+
+pub use claims::{
+    AccessClaims, AdminClaims, GuestClaims, OperatorClaims, ParticipantClaims, RefreshClaims,
+    ResearcherClaims, RobotClaims,
+};
@@ -1,7 +1,10 @@
+pub mod config;
 pub mod config_getter;
 pub mod constants;
 pub mod cors;
+pub mod errors;
 pub mod guards;
 pub mod models;
+pub mod security;
 pub mod services;
 pub mod traits;
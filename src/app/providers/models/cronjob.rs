@@ -1,4 +1,6 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+#![allow(unused)]
+
+use chrono::{DateTime, Utc};
 use rocket::serde::uuid::Uuid;
 use serde::{Deserialize, Serialize};
 
@@ -18,8 +20,10 @@ pub struct PubEJob {
     pub id: Uuid,
     pub status: String,
     pub schedule: String,
-    pub since: Option<NaiveDateTime>,
-    pub until: Option<NaiveDateTime>,
+    pub timezone: String,
+    pub run_at: Option<DateTime<Utc>>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -33,9 +37,23 @@ pub struct PubNewCronJob {
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct NewEJob {
+    /// Cron expression on the wall clock of `timezone`, empty with `run_at`.
+    #[serde(default)]
     pub schedule: String,
-    pub since: Option<NaiveDateTime>,
-    pub until: Option<NaiveDateTime>,
+    /// IANA name, `UTC` when missing.
+    #[serde(default = "NewEJob::default_timezone")]
+    pub timezone: String,
+    /// When a one-shot job runs.
+    #[serde(default)]
+    pub run_at: Option<DateTime<Utc>>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl NewEJob {
+    fn default_timezone() -> String {
+        "UTC".to_string()
+    }
 }
 
 impl From<PubCronJob> for PubNewCronJob {
@@ -45,6 +63,8 @@ impl From<PubCronJob> for PubNewCronJob {
             route: cronjob.route,
             job: NewEJob {
                 schedule: cronjob.job.schedule,
+                timezone: cronjob.job.timezone,
+                run_at: cronjob.job.run_at,
                 since: cronjob.job.since,
                 until: cronjob.job.until,
             },
@@ -1,3 +1,5 @@
+#![allow(unused)]
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
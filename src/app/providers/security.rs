@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::{ContentType, Header};
+use rocket::{Build, Request, Response, Rocket};
+use serde::Deserialize;
+
+/// Security headers read from the `security_headers` table of Rocket.toml.
+/// `headers` go on every response and `html` only on html ones. `groups`
+/// overrides them for every route under a path prefix, longer prefixes
+/// winning. An empty value removes the header.
+///
+/// The tables are merged over the defaults header by header, so unsetting a
+/// default takes an empty value.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct SecurityHeadersConfig {
+    pub headers: BTreeMap<String, String>,
+    pub html: BTreeMap<String, String>,
+    pub groups: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        let table = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        SecurityHeadersConfig {
+            headers: table(&[
+                (
+                    "Strict-Transport-Security",
+                    "max-age=63072000; includeSubDomains",
+                ),
+                ("X-Content-Type-Options", "nosniff"),
+                ("Referrer-Policy", "no-referrer"),
+            ]),
+            html: table(&[
+                (
+                    "Content-Security-Policy",
+                    "default-src 'self'; frame-ancestors 'none'; form-action 'self'",
+                ),
+                ("X-Frame-Options", "DENY"),
+            ]),
+            groups: BTreeMap::from([(
+                "/auth".to_string(),
+                table(&[("Cache-Control", "no-store"), ("Pragma", "no-cache")]),
+            )]),
+        }
+    }
+}
+
+impl SecurityHeadersConfig {
+    /// `configured` laid over `self`, header by header.
+    pub fn merge(mut self, configured: SecurityHeadersConfig) -> Self {
+        self.headers.extend(configured.headers);
+        self.html.extend(configured.html);
+        for (prefix, group) in configured.groups {
+            self.groups.entry(prefix).or_default().extend(group);
+        }
+
+        self
+    }
+
+    /// Headers for a response to `path`, group overrides already applied.
+    pub fn for_path(&self, path: &str, html: bool) -> BTreeMap<String, String> {
+        let mut headers = self.headers.clone();
+        if html {
+            headers.extend(self.html.clone());
+        }
+
+        let mut groups = self
+            .groups
+            .iter()
+            .filter(|(prefix, _)| in_group(prefix, path))
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|(prefix, _)| prefix.len());
+
+        for (_, group) in groups {
+            headers.extend(group.clone());
+        }
+
+        headers
+    }
+}
+
+fn in_group(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+pub struct SecurityHeaders;
+
+#[rocket::async_trait]
+impl Fairing for SecurityHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Security headers",
+            kind: Kind::Ignite | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let config = match rocket
+            .figment()
+            .extract_inner::<SecurityHeadersConfig>("security_headers")
+        {
+            Ok(config) => SecurityHeadersConfig::default().merge(config),
+            Err(e) if e.missing() => SecurityHeadersConfig::default(),
+            Err(e) => {
+                error!("ERROR: security_headers; invalid config; {e}");
+                return Err(rocket);
+            }
+        };
+
+        Ok(rocket.manage(config))
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let config = match request.rocket().state::<SecurityHeadersConfig>() {
+            Some(config) => config,
+            None => return,
+        };
+
+        let html = response.content_type() == Some(ContentType::HTML);
+        let path = request.uri().path();
+
+        for (name, value) in config.for_path(path.as_str(), html) {
+            if value.is_empty() {
+                response.remove_header(&name);
+            } else {
+                response.set_header(Header::new(name, value));
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+use std::sync::RwLock;
+
+use super::{AuditFilter, AuditLog, AuthEvent, NewAuthEvent};
+
+const CAPACITY: usize = 10_000;
+
+/// Keeps the latest events of this instance, for builds without a database.
+pub struct MemoryAuditLog {
+    events: RwLock<VecDeque<AuthEvent>>,
+    capacity: usize,
+}
+
+impl Default for MemoryAuditLog {
+    fn default() -> Self {
+        MemoryAuditLog::with_capacity(CAPACITY)
+    }
+}
+
+impl MemoryAuditLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        MemoryAuditLog {
+            events: RwLock::new(VecDeque::new()),
+            capacity,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl AuditLog for MemoryAuditLog {
+    async fn append(&self, event: NewAuthEvent) -> Result<(), String> {
+        let mut events = self.events.write().unwrap_or_else(|e| e.into_inner());
+        let id = events.back().map_or(1, |last| last.id + 1);
+
+        if events.len() >= self.capacity {
+            events.pop_front();
+        }
+        events.push_back(AuthEvent {
+            id,
+            occurred_at: chrono::Utc::now(),
+            kind: event.kind.as_str().to_string(),
+            outcome: event.outcome,
+            actor_id: event.actor_id,
+            subject_id: event.subject_id,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            client: event.client,
+        });
+
+        Ok(())
+    }
+
+    async fn query(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuthEvent>, String> {
+        let events = self.events.read().unwrap_or_else(|e| e.into_inner());
+
+        Ok(events
+            .iter()
+            .rev()
+            .filter(|event| filter.matches(event))
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+}
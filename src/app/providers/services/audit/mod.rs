@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rocket::fairing::AdHoc;
+#[cfg(feature = "db_sqlx")]
+use rocket_db_pools::sqlx::{Postgres, Transaction};
+use serde::Serialize;
+
+pub mod memory;
+#[cfg(feature = "db_sqlx")]
+pub mod postgres;
+
+pub use memory::MemoryAuditLog;
+#[cfg(feature = "db_sqlx")]
+pub use postgres::PostgresAuditLog;
+
+/// Outcome recorded for events that went through.
+pub const SUCCESS: &str = "success";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum EventKind {
+    Login,
+    GuestCreated,
+    Refresh,
+    ProjectSwitch,
+    Logout,
+    Revocation,
+    Bypass,
+    /// A token issued to an actor on behalf of another user. Reserved, no
+    /// impersonation flow exists yet.
+    #[allow(dead_code)]
+    Impersonation,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 8] = [
+        EventKind::Login,
+        EventKind::GuestCreated,
+        EventKind::Refresh,
+        EventKind::ProjectSwitch,
+        EventKind::Logout,
+        EventKind::Revocation,
+        EventKind::Bypass,
+        EventKind::Impersonation,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Login => "login",
+            EventKind::GuestCreated => "guest_created",
+            EventKind::Refresh => "refresh",
+            EventKind::ProjectSwitch => "project_switch",
+            EventKind::Logout => "logout",
+            EventKind::Revocation => "revocation",
+            EventKind::Bypass => "bypass",
+            EventKind::Impersonation => "impersonation",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<EventKind> {
+        EventKind::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+}
+
+/// Event about to be appended. `actor_id` is who acted, `subject_id` whose
+/// tokens it was about; they only differ for revocations and impersonation.
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewAuthEvent {
+    pub kind: EventKind,
+    pub outcome: String,
+    pub actor_id: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub client: Option<String>,
+}
+
+/// Row of the `auth_events` table.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "db_sqlx", derive(rocket_db_pools::sqlx::FromRow))]
+#[serde(crate = "rocket::serde")]
+pub struct AuthEvent {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub kind: String,
+    pub outcome: String,
+    pub actor_id: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub client: Option<String>,
+}
+
+impl AuthEvent {
+    pub const CSV_HEADER: &'static str =
+        "id,occurred_at,kind,outcome,actor_id,subject_id,ip,user_agent,client";
+
+    pub fn to_csv(&self) -> String {
+        let optional = |value: Option<String>| csv_field(&value.unwrap_or_default());
+
+        [
+            self.id.to_string(),
+            self.occurred_at.to_rfc3339(),
+            csv_field(&self.kind),
+            csv_field(&self.outcome),
+            optional(self.actor_id.map(|id| id.to_string())),
+            optional(self.subject_id.map(|id| id.to_string())),
+            optional(self.ip.clone()),
+            optional(self.user_agent.clone()),
+            optional(self.client.clone()),
+        ]
+        .join(",")
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Filters of `GET /admin/audit`. `before` is the cursor, the id of the last
+/// event of the previous page.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub kind: Option<EventKind>,
+    pub outcome: Option<String>,
+    pub actor_id: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub ip: Option<String>,
+    pub client: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub before: Option<i64>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, event: &AuthEvent) -> bool {
+        self.kind.is_none_or(|kind| event.kind == kind.as_str())
+            && self
+                .outcome
+                .as_ref()
+                .is_none_or(|outcome| &event.outcome == outcome)
+            && self.actor_id.is_none_or(|id| event.actor_id == Some(id))
+            && self
+                .subject_id
+                .is_none_or(|id| event.subject_id == Some(id))
+            && self
+                .ip
+                .as_ref()
+                .is_none_or(|ip| event.ip.as_ref() == Some(ip))
+            && self
+                .client
+                .as_ref()
+                .is_none_or(|client| event.client.as_ref() == Some(client))
+            && self.since.is_none_or(|since| event.occurred_at >= since)
+            && self.until.is_none_or(|until| event.occurred_at < until)
+            && self.before.is_none_or(|before| event.id < before)
+    }
+}
+
+/// Append only store of authentication events.
+#[rocket::async_trait]
+pub trait AuditLog: Send + Sync {
+    async fn append(&self, event: NewAuthEvent) -> Result<(), String>;
+    /// `append` inside `tx`, so the effects of the event are enqueued with
+    /// it. Logs outside the database write on their own.
+    #[cfg(feature = "db_sqlx")]
+    async fn append_in(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: NewAuthEvent,
+    ) -> Result<(), String> {
+        let _ = tx;
+        self.append(event).await
+    }
+    /// Newest first, at most `limit` events.
+    async fn query(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuthEvent>, String>;
+}
+
+/// Manages the `auth_events` table store with `db_sqlx`, or an in memory
+/// one otherwise. Attach after the database fairings.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("Init audit log", |rocket| async {
+        if rocket.state::<Arc<dyn AuditLog>>().is_some() {
+            return rocket;
+        }
+
+        #[cfg(feature = "db_sqlx")]
+        {
+            use rocket_db_pools::Database;
+
+            if let Some(db) = crate::database::connection::Db::fetch(&rocket) {
+                let log: Arc<dyn AuditLog> = Arc::new(PostgresAuditLog::new(db.0.clone()));
+                return rocket.manage(log);
+            }
+        }
+
+        info!("AUDIT: no database, events are only kept in memory");
+        let log: Arc<dyn AuditLog> = Arc::new(MemoryAuditLog::default());
+        rocket.manage(log)
+    })
+}
@@ -0,0 +1,94 @@
+use rocket_db_pools::sqlx::{self, PgPool, Postgres, QueryBuilder, Transaction};
+
+use super::{AuditFilter, AuditLog, AuthEvent, NewAuthEvent};
+
+/// The `auth_events` table. Its trigger rejects updates and deletes.
+pub struct PostgresAuditLog(PgPool);
+
+impl PostgresAuditLog {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresAuditLog(pool)
+    }
+}
+
+fn insert(
+    event: NewAuthEvent,
+) -> sqlx::query::Query<'static, Postgres, sqlx::postgres::PgArguments> {
+    sqlx::query(
+        "INSERT INTO auth_events (kind, outcome, actor_id, subject_id, ip, user_agent, client) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(event.kind.as_str())
+    .bind(event.outcome)
+    .bind(event.actor_id)
+    .bind(event.subject_id)
+    .bind(event.ip)
+    .bind(event.user_agent)
+    .bind(event.client)
+}
+
+#[rocket::async_trait]
+impl AuditLog for PostgresAuditLog {
+    async fn append(&self, event: NewAuthEvent) -> Result<(), String> {
+        insert(event)
+            .execute(&self.0)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn append_in(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: NewAuthEvent,
+    ) -> Result<(), String> {
+        insert(event)
+            .execute(&mut **tx)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn query(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuthEvent>, String> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT id, occurred_at, kind, outcome, actor_id, subject_id, ip, user_agent, \
+             client FROM auth_events WHERE TRUE",
+        );
+
+        if let Some(kind) = filter.kind {
+            query.push(" AND kind = ").push_bind(kind.as_str());
+        }
+        if let Some(outcome) = &filter.outcome {
+            query.push(" AND outcome = ").push_bind(outcome.clone());
+        }
+        if let Some(actor_id) = filter.actor_id {
+            query.push(" AND actor_id = ").push_bind(actor_id);
+        }
+        if let Some(subject_id) = filter.subject_id {
+            query.push(" AND subject_id = ").push_bind(subject_id);
+        }
+        if let Some(ip) = &filter.ip {
+            query.push(" AND ip = ").push_bind(ip.clone());
+        }
+        if let Some(client) = &filter.client {
+            query.push(" AND client = ").push_bind(client.clone());
+        }
+        if let Some(since) = filter.since {
+            query.push(" AND occurred_at >= ").push_bind(since);
+        }
+        if let Some(until) = filter.until {
+            query.push(" AND occurred_at < ").push_bind(until);
+        }
+        if let Some(before) = filter.before {
+            query.push(" AND id < ").push_bind(before);
+        }
+
+        query.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+        query
+            .build_query_as::<AuthEvent>()
+            .fetch_all(&self.0)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
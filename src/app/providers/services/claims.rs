@@ -1,63 +1,46 @@
 #![allow(dead_code)]
 
-use jsonwebtoken::errors::Error;
-use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
-use serde::{Deserialize, Serialize};
+use jsonwebtoken::errors::{Error, ErrorKind};
 
-use crate::app::providers::constants::{
-    ACCESS_TOKEN_EXPIRATION, REFRESH_TOKEN_EXPIRATION, ROBOT_TOKEN_EXPIRATION,
-};
-
-use crate::app::providers::config_getter::ConfigGetter;
-use crate::app::providers::models::user::PubUserExpanded;
+use crate::app::providers::config::AuthConfig;
+use crate::app::providers::models::user::{PubUserExpanded, UserProject};
 
-#[derive(Debug)]
-pub enum ClaimsError {
-    MissingToken,
-    InvalidToken,
-}
+pub use q_auth_client::claims::{
+    Claims, ClaimsError, ProjectInClaims, Role, RoleInClaims, UserInClaims,
+};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(crate = "rocket::serde")]
-pub struct RoleInClaims {
-    pub id: i32,
-    pub name: String,
+/// Signing with the lifetimes and the signing key of `AuthConfig`.
+pub trait EncodeClaims {
+    fn enconde_for_robot(&mut self, config: &AuthConfig) -> Result<String, Error>;
+    fn encode_for_access(&mut self, config: &AuthConfig) -> Result<String, Error>;
+    fn encode_for_refresh(&mut self, config: &AuthConfig) -> Result<String, Error>;
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(crate = "rocket::serde")]
-pub struct UserInClaims {
-    pub id: i32,
-    pub depends_on: i32,
-    pub role: RoleInClaims,
-    pub user_token: Option<String>,
-}
+impl EncodeClaims for Claims {
+    fn enconde_for_robot(&mut self, config: &AuthConfig) -> Result<String, Error> {
+        self.user.role = Role::Robot.into();
+        self.user.user_token = None;
 
-impl Default for UserInClaims {
-    fn default() -> Self {
-        UserInClaims {
-            id: 0,
-            depends_on: 0,
-            role: RoleInClaims {
-                id: 6,
-                name: String::from("Guest"),
-            },
-            user_token: None,
-        }
+        self.expire_in(config.robot_token_expiration)
+            .sign(config.keys.signing())
     }
-}
 
-impl From<UserInClaims> for Claims {
-    fn from(user: UserInClaims) -> Self {
-        let iat = chrono::Utc::now().timestamp();
-        let exp = iat + ROBOT_TOKEN_EXPIRATION;
+    fn encode_for_access(&mut self, config: &AuthConfig) -> Result<String, Error> {
+        self.user.user_token = None;
+
+        self.expire_in(config.access_token_expiration)
+            .sign(config.keys.signing())
+    }
 
-        Claims {
-            sub: String::new(),
-            user,
-            iat,
-            exp,
+    /// Refresh tokens carry the `user_token` of the user service, without it
+    /// there is nothing to refresh with.
+    fn encode_for_refresh(&mut self, config: &AuthConfig) -> Result<String, Error> {
+        if self.user.user_token.is_none() {
+            return Err(ErrorKind::MissingRequiredClaim("user_token".to_string()).into());
         }
+
+        self.expire_in(config.refresh_token_expiration)
+            .sign(config.keys.signing())
     }
 }
 
@@ -71,83 +54,18 @@ impl From<PubUserExpanded> for UserInClaims {
                 name: user.role.name,
             },
             user_token: user.user_token,
+            project: Some(user.project.into()),
+            expires_at: None,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(crate = "rocket::serde")]
-pub struct Claims {
-    pub sub: String,
-    pub user: UserInClaims,
-    pub iat: i64,
-    pub exp: i64,
-}
-
-impl Claims {
-    pub fn enconde_for_robot(&mut self) -> Result<String, Error> {
-        let iat = chrono::Utc::now().timestamp();
-        let exp = iat + ROBOT_TOKEN_EXPIRATION;
-
-        self.user.role.id = 5;
-        self.user.role.name = String::from("robot");
-
-        self.iat = iat;
-        self.exp = exp;
-        self.user.user_token = None;
-
-        let secret_key = match ConfigGetter::get_secret_key() {
-            None => panic!("secret_key is mandatory"),
-            Some(secret_key) => secret_key,
-        };
-
-        encode(
-            &Header::new(Algorithm::HS256),
-            &self,
-            &EncodingKey::from_secret(secret_key.as_ref()),
-        )
-    }
-
-    pub fn encode_for_access(&mut self) -> Result<String, Error> {
-        let iat = chrono::Utc::now().timestamp();
-        let exp = iat + ACCESS_TOKEN_EXPIRATION;
-
-        self.iat = iat;
-        self.exp = exp;
-        self.user.user_token = None;
-
-        let secret_key = match ConfigGetter::get_secret_key() {
-            None => panic!("secret_key is mandatory"),
-            Some(secret_key) => secret_key,
-        };
-
-        encode(
-            &Header::new(Algorithm::HS256),
-            &self,
-            &EncodingKey::from_secret(secret_key.as_ref()),
-        )
-    }
-
-    pub fn encode_for_refresh(&mut self) -> Result<String, Error> {
-        let iat = chrono::Utc::now().timestamp();
-        let exp = iat + REFRESH_TOKEN_EXPIRATION;
-
-        self.iat = iat;
-        self.exp = exp;
-
-        if self.user.user_token.is_none() {
-            panic!("user_token is mandatory for refresh token");
+impl From<UserProject> for ProjectInClaims {
+    fn from(membership: UserProject) -> Self {
+        ProjectInClaims {
+            id: membership.project_id,
+            active: membership.active,
+            keys: membership.keys,
         }
-
-        let secret_key = match ConfigGetter::get_secret_key() {
-            None => panic!("secret_key is mandatory"),
-            Some(secret_key) => secret_key,
-        };
-
-        encode(
-            &Header::new(Algorithm::HS256),
-            &self,
-            &EncodingKey::from_secret(secret_key.as_ref()),
-        )
     }
 }
@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+
+use reqwest::Method;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::models::message::{PubNewToken, PubToken};
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::{self, UpstreamError};
+
+#[rocket::async_trait]
+pub trait MessagingClient: Send + Sync {
+    /// Creates the empty token row of a new user.
+    async fn init_user(&self, user_id: i32) -> Result<PubToken, UpstreamError>;
+    /// Drops the fcm and web push tokens of the user.
+    async fn reset_tokens(&self, user_id: i32) -> Result<(), UpstreamError>;
+    /// Deletes the token row created by `init_user`.
+    async fn delete_user(&self, user_id: i32) -> Result<(), UpstreamError>;
+}
+
+pub struct HttpMessagingClient {
+    fetch: Fetch,
+    config: SharedConfig,
+}
+
+impl HttpMessagingClient {
+    pub fn new(fetch: Fetch, config: SharedConfig) -> Self {
+        HttpMessagingClient { fetch, config }
+    }
+}
+
+#[rocket::async_trait]
+impl MessagingClient for HttpMessagingClient {
+    async fn init_user(&self, user_id: i32) -> Result<PubToken, UpstreamError> {
+        let new_token = PubNewToken {
+            user_id,
+            fcm_token: None,
+            web_token: None,
+        };
+
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "message",
+            Method::POST,
+            "token/",
+        )
+        .await?
+        .json(&new_token)
+        .send()
+        .await;
+
+        upstream::read_json::<PubToken>("message", res).await
+    }
+
+    async fn reset_tokens(&self, user_id: i32) -> Result<(), UpstreamError> {
+        let new_token = PubNewToken {
+            user_id,
+            fcm_token: None,
+            web_token: None,
+        };
+
+        let path = format!("token/user/{user_id}");
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "message",
+            Method::PUT,
+            &path,
+        )
+        .await?
+        .json(&new_token)
+        .send()
+        .await;
+
+        upstream::expect_success("message", res).await?;
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<(), UpstreamError> {
+        let path = format!("token/user/{user_id}");
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "message",
+            Method::DELETE,
+            &path,
+        )
+        .await?
+        .send()
+        .await;
+
+        upstream::expect_success("message", res).await?;
+
+        Ok(())
+    }
+}
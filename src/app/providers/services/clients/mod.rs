@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use reqwest::{Method, RequestBuilder};
+use rocket::fairing::AdHoc;
+use rocket::{Build, Rocket};
+
+use crate::app::providers::config::{AuthConfig, SharedConfig};
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::{UpstreamError, UpstreamErrorKind};
+
+pub mod messaging;
+pub mod profile;
+pub mod project;
+pub mod user;
+
+pub use messaging::{HttpMessagingClient, MessagingClient};
+pub use profile::{HttpProfileClient, ProfileClient};
+pub use project::{HttpProjectClient, ProjectClient};
+pub use user::{HttpUserClient, UserClient};
+
+/// Builds a request to `service` authenticated with a fresh robot token.
+pub(crate) async fn robot_request(
+    fetch: &Fetch,
+    config: &AuthConfig,
+    service: &'static str,
+    method: Method,
+    path: &str,
+) -> Result<RequestBuilder, UpstreamError> {
+    let base_url = config
+        .entity_url(service)
+        .ok_or_else(|| UpstreamError::new(service, UpstreamErrorKind::NotConfigured))?;
+
+    let robot_token = Fetch::robot_token(config)
+        .await
+        .map_err(|e| UpstreamError {
+            service,
+            kind: UpstreamErrorKind::Request,
+            excerpt: Some(format!("robot token; {e}")),
+        })?;
+
+    Ok(fetch
+        .client
+        .request(method, format!("{base_url}{path}"))
+        .timeout(fetch.timeout_for(service))
+        .header("Accept", "application/json")
+        .header("Authorization", robot_token))
+}
+
+/// Manages the http implementation of every client that is not already
+/// managed, so tests can register their own fakes before ignite.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Init service clients", |rocket| async {
+        // Fetch or AuthConfig failed to load and already reported why
+        let (fetch, config) = match (rocket.state::<Fetch>(), rocket.state::<SharedConfig>()) {
+            (Some(fetch), Some(config)) => (fetch.clone(), config.clone()),
+            _ => return Err(rocket),
+        };
+
+        let rocket = manage_default::<dyn ProfileClient>(
+            rocket,
+            Arc::new(HttpProfileClient::new(fetch.clone(), config.clone())),
+        );
+        let rocket = manage_default::<dyn UserClient>(
+            rocket,
+            Arc::new(HttpUserClient::new(fetch.clone(), config.clone())),
+        );
+        let rocket = manage_default::<dyn MessagingClient>(
+            rocket,
+            Arc::new(HttpMessagingClient::new(fetch.clone(), config.clone())),
+        );
+
+        Ok(manage_default::<dyn ProjectClient>(
+            rocket,
+            Arc::new(HttpProjectClient::new(fetch, config)),
+        ))
+    })
+}
+
+fn manage_default<T: ?Sized + Send + Sync + 'static>(
+    rocket: Rocket<Build>,
+    client: Arc<T>,
+) -> Rocket<Build> {
+    if rocket.state::<Arc<T>>().is_some() {
+        return rocket;
+    }
+
+    rocket.manage(client)
+}
@@ -0,0 +1,41 @@
+use reqwest::Method;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::{self, UpstreamError};
+
+#[rocket::async_trait]
+pub trait ProfileClient: Send + Sync {
+    /// Resolves a profile token to the id of the user it belongs to.
+    async fn verify_token(&self, token: &str) -> Result<i32, UpstreamError>;
+}
+
+pub struct HttpProfileClient {
+    fetch: Fetch,
+    config: SharedConfig,
+}
+
+impl HttpProfileClient {
+    pub fn new(fetch: Fetch, config: SharedConfig) -> Self {
+        HttpProfileClient { fetch, config }
+    }
+}
+
+#[rocket::async_trait]
+impl ProfileClient for HttpProfileClient {
+    async fn verify_token(&self, token: &str) -> Result<i32, UpstreamError> {
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "profile",
+            Method::POST,
+            "token",
+        )
+        .await?
+        .json(&token)
+        .send()
+        .await;
+
+        upstream::read_json::<i32>("profile", res).await
+    }
+}
@@ -0,0 +1,99 @@
+#![allow(dead_code)]
+
+use reqwest::Method;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::models::project::PubProject;
+use crate::app::providers::models::record::{PubNewRecord, PubRecord};
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::{self, UpstreamError};
+
+#[rocket::async_trait]
+pub trait ProjectClient: Send + Sync {
+    /// Registers the user in the project and creates its first record.
+    async fn init_user(
+        &self,
+        project_id: i32,
+        user_id: i32,
+    ) -> Result<PubProject, UpstreamError>;
+    /// Undoes `init_user`, records included.
+    async fn remove_user(&self, project_id: i32, user_id: i32) -> Result<(), UpstreamError>;
+    async fn store_record(
+        &self,
+        project_id: i32,
+        new_record: &PubNewRecord,
+    ) -> Result<PubRecord, UpstreamError>;
+}
+
+pub struct HttpProjectClient {
+    fetch: Fetch,
+    config: SharedConfig,
+}
+
+impl HttpProjectClient {
+    pub fn new(fetch: Fetch, config: SharedConfig) -> Self {
+        HttpProjectClient { fetch, config }
+    }
+}
+
+#[rocket::async_trait]
+impl ProjectClient for HttpProjectClient {
+    async fn init_user(
+        &self,
+        project_id: i32,
+        user_id: i32,
+    ) -> Result<PubProject, UpstreamError> {
+        let path = format!("{project_id}/user/{user_id}/new");
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "project",
+            Method::GET,
+            &path,
+        )
+        .await?
+        .send()
+        .await;
+
+        upstream::read_json::<PubProject>("project", res).await
+    }
+
+    async fn remove_user(&self, project_id: i32, user_id: i32) -> Result<(), UpstreamError> {
+        let path = format!("{project_id}/user/{user_id}");
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "project",
+            Method::DELETE,
+            &path,
+        )
+        .await?
+        .send()
+        .await;
+
+        upstream::expect_success("project", res).await?;
+
+        Ok(())
+    }
+
+    async fn store_record(
+        &self,
+        project_id: i32,
+        new_record: &PubNewRecord,
+    ) -> Result<PubRecord, UpstreamError> {
+        let path = format!("{project_id}/record");
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "project",
+            Method::POST,
+            &path,
+        )
+        .await?
+        .json(new_record)
+        .send()
+        .await;
+
+        upstream::read_json::<PubRecord>("project", res).await
+    }
+}
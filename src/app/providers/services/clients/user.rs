@@ -0,0 +1,96 @@
+use reqwest::Method;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::models::user::{PubNewUser, PubUserExpanded, UserProject};
+use crate::app::providers::services::claims::UserInClaims;
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::{self, UpstreamError};
+
+#[rocket::async_trait]
+pub trait UserClient: Send + Sync {
+    async fn user_in_claims(&self, user_id: i32) -> Result<UserInClaims, UpstreamError>;
+    async fn create(&self, new_user: &PubNewUser) -> Result<PubUserExpanded, UpstreamError>;
+    /// Every project the user belongs to, inactive memberships included.
+    async fn memberships(&self, user_id: i32) -> Result<Vec<UserProject>, UpstreamError>;
+    async fn delete(&self, user_id: i32) -> Result<(), UpstreamError>;
+    /// Strips the personal data of the user, its records stay.
+    async fn anonymize(&self, user_id: i32) -> Result<(), UpstreamError>;
+}
+
+pub struct HttpUserClient {
+    fetch: Fetch,
+    config: SharedConfig,
+}
+
+impl HttpUserClient {
+    pub fn new(fetch: Fetch, config: SharedConfig) -> Self {
+        HttpUserClient { fetch, config }
+    }
+}
+
+#[rocket::async_trait]
+impl UserClient for HttpUserClient {
+    async fn user_in_claims(&self, user_id: i32) -> Result<UserInClaims, UpstreamError> {
+        let path = format!("{user_id}/userinclaims");
+        let res =
+            super::robot_request(&self.fetch, &self.config.get(), "user", Method::GET, &path)
+                .await?
+                .send()
+                .await;
+
+        upstream::read_json::<UserInClaims>("user", res).await
+    }
+
+    async fn create(&self, new_user: &PubNewUser) -> Result<PubUserExpanded, UpstreamError> {
+        let res =
+            super::robot_request(&self.fetch, &self.config.get(), "user", Method::POST, "")
+                .await?
+                .json(new_user)
+                .send()
+                .await;
+
+        upstream::read_json::<PubUserExpanded>("user", res).await
+    }
+
+    async fn memberships(&self, user_id: i32) -> Result<Vec<UserProject>, UpstreamError> {
+        let path = format!("{user_id}/projects");
+        let res =
+            super::robot_request(&self.fetch, &self.config.get(), "user", Method::GET, &path)
+                .await?
+                .send()
+                .await;
+
+        upstream::read_json::<Vec<UserProject>>("user", res).await
+    }
+
+    async fn delete(&self, user_id: i32) -> Result<(), UpstreamError> {
+        let path = user_id.to_string();
+        let res = super::robot_request(
+            &self.fetch,
+            &self.config.get(),
+            "user",
+            Method::DELETE,
+            &path,
+        )
+        .await?
+        .send()
+        .await;
+
+        upstream::expect_success("user", res).await?;
+
+        Ok(())
+    }
+
+    async fn anonymize(&self, user_id: i32) -> Result<(), UpstreamError> {
+        let path = format!("{user_id}/anonymize");
+        let res =
+            super::robot_request(&self.fetch, &self.config.get(), "user", Method::PUT, &path)
+                .await?
+                .send()
+                .await;
+
+        upstream::expect_success("user", res).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rocket::time::Duration;
+use serde::Deserialize;
+
+const CSRF_TOKEN_LEN: usize = 32;
+
+/// Raw `refresh_cookie` table of Rocket.toml. Override it per profile, e.g.
+/// `secure = false` under `[debug.refresh_cookie]`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct RefreshCookieConfig {
+    pub name: String,
+    pub path: String,
+    pub domain: Option<String>,
+    pub same_site: String,
+    pub secure: bool,
+    pub csrf: String,
+    pub csrf_cookie: String,
+    pub csrf_header: String,
+}
+
+impl Default for RefreshCookieConfig {
+    fn default() -> Self {
+        RefreshCookieConfig {
+            name: "refresh_token".to_string(),
+            path: "/auth".to_string(),
+            domain: None,
+            same_site: "strict".to_string(),
+            secure: true,
+            csrf: "origin".to_string(),
+            csrf_cookie: "csrf_token".to_string(),
+            csrf_header: "X-CSRF-Token".to_string(),
+        }
+    }
+}
+
+/// How the cookie authenticated endpoints are protected against CSRF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsrfMode {
+    /// `Origin`, or `Referer` when missing, must be an allowed origin.
+    Origin,
+    /// A header must repeat the value of the readable csrf cookie.
+    DoubleSubmit {
+        cookie: String,
+        header: String,
+    },
+    Off,
+}
+
+/// Validated attributes of the refresh cookie.
+#[derive(Debug, Clone)]
+pub struct CookiePolicy {
+    pub name: String,
+    pub path: String,
+    pub domain: Option<String>,
+    pub same_site: SameSite,
+    pub secure: bool,
+    pub csrf: CsrfMode,
+}
+
+impl TryFrom<RefreshCookieConfig> for CookiePolicy {
+    type Error = Vec<String>;
+
+    fn try_from(raw: RefreshCookieConfig) -> Result<Self, Self::Error> {
+        let mut errors = Vec::new();
+
+        if raw.name.trim().is_empty() {
+            errors.push("refresh_cookie.name is mandatory".to_string());
+        }
+        if !raw.path.starts_with('/') {
+            errors.push("refresh_cookie.path must start with a slash".to_string());
+        }
+
+        let same_site = match raw.same_site.to_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            other => {
+                errors.push(format!(
+                    "refresh_cookie.same_site `{other}` is not strict, lax or none"
+                ));
+                SameSite::Strict
+            }
+        };
+        if same_site == SameSite::None && !raw.secure {
+            errors.push("refresh_cookie.same_site none requires secure".to_string());
+        }
+
+        let csrf = match raw.csrf.to_lowercase().as_str() {
+            "origin" => CsrfMode::Origin,
+            "double_submit" => CsrfMode::DoubleSubmit {
+                cookie: raw.csrf_cookie.clone(),
+                header: raw.csrf_header.clone(),
+            },
+            "off" => CsrfMode::Off,
+            other => {
+                errors.push(format!(
+                    "refresh_cookie.csrf `{other}` is not origin, double_submit or off"
+                ));
+                CsrfMode::Off
+            }
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(CookiePolicy {
+            name: raw.name,
+            path: raw.path,
+            domain: raw.domain.filter(|domain| !domain.is_empty()),
+            same_site,
+            secure: raw.secure,
+            csrf,
+        })
+    }
+}
+
+impl CookiePolicy {
+    fn build(&self, name: String, value: String, max_age: i64) -> Cookie<'static> {
+        self.build_at(self.path.clone(), name, value, max_age)
+    }
+
+    /// Cookie with the policy attributes but its own path, for cookies the
+    /// whole site needs such as the BFF session.
+    pub fn build_at(
+        &self,
+        path: String,
+        name: String,
+        value: String,
+        max_age: i64,
+    ) -> Cookie<'static> {
+        let mut cookie = Cookie::build((name, value))
+            .path(path)
+            .same_site(self.same_site)
+            .secure(self.secure)
+            .max_age(Duration::seconds(max_age));
+
+        if let Some(domain) = &self.domain {
+            cookie = cookie.domain(domain.clone());
+        }
+
+        cookie.build()
+    }
+
+    /// Stores the refresh token, and a fresh csrf token in double submit
+    /// mode. `max_age` is the lifetime of the refresh token.
+    pub fn set(&self, jar: &CookieJar<'_>, refresh_token: String, max_age: i64) {
+        let mut cookie = self.build(self.name.clone(), refresh_token, max_age);
+        cookie.set_http_only(true);
+        jar.add_private(cookie);
+
+        if let CsrfMode::DoubleSubmit { cookie, .. } = &self.csrf {
+            // Scripts read it from any page, not only under the refresh path
+            let token = random_token(CSRF_TOKEN_LEN);
+            let mut cookie = self.build_at("/".to_string(), cookie.clone(), token, max_age);
+            cookie.set_http_only(false);
+            jar.add(cookie);
+        }
+    }
+
+    /// Removes the refresh cookie, and the csrf one, from the browser.
+    pub fn clear(&self, jar: &CookieJar<'_>) {
+        jar.remove_private(self.build(self.name.clone(), String::new(), 0));
+
+        if let CsrfMode::DoubleSubmit { cookie, .. } = &self.csrf {
+            jar.remove(self.build_at("/".to_string(), cookie.clone(), String::new(), 0));
+        }
+    }
+}
+
+/// Random alphanumeric token for cookie values.
+pub fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
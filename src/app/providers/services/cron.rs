@@ -1,41 +1,33 @@
 use rocket_db_pools::{sqlx, Database};
 
 use escalon_jobs::manager::{ContextTrait, EscalonJobsManager, EscalonJobsManagerTrait};
-use escalon_jobs::{EscalonJob, EscalonJobStatus, EscalonJobTrait, NewEscalonJob};
-use rocket::fairing::{Fairing, Info, Kind};
+use escalon_jobs::EscalonJob;
 use rocket::{async_trait, Build, Rocket};
 use sqlx::types::Uuid;
-use std::borrow::BorrowMut;
 use std::net::IpAddr;
-use std::sync::Arc;
-use std::time::Duration;
 
-use crate::app::modules::cron::model::{CronJob, CronJobComplete, NewCronJob};
-use crate::app::modules::escalon::model::{EJob, NewEJob};
+use crate::app::modules::cron::services::{misfire, repository};
+use crate::app::modules::escalon::model::{self as escalon, EJob, NewEJob};
+use crate::app::providers::config::SharedConfig;
 use crate::app::providers::config_getter::ConfigGetter;
+use crate::app::providers::services::fetch::Fetch;
 use crate::database::connection::Db;
 
 #[derive(Clone)]
 pub struct Context {
     pub db: sqlx::PgPool,
-    pub fetch: reqwest::Client,
+    pub fetch: Fetch,
+    pub config: SharedConfig,
 }
 
 #[async_trait]
 impl ContextTrait<Context> for Context {
     async fn update_job(&self, context: &Context, job: EscalonJob) {
-        let job: EJob = job.into();
-
-        sqlx::query!(
-            r#"
-            UPDATE escalonjobs SET status = $1 WHERE id = $2
-            "#,
-            job.status,
-            job.id,
-        )
-        .execute(&context.db)
-        .await
-        .unwrap();
+        let status = escalon::status(&job.status);
+
+        if let Err(e) = repository::record_status(&context.db, job.job_id, &status).await {
+            error!("CRON: status of job {} could not be saved; {e}", job.job_id);
+        }
     }
 }
 
@@ -43,18 +35,26 @@ pub struct CronManager(pub EscalonJobsManager<Context>);
 
 impl CronManager {
     pub async fn init(rocket: Rocket<Build>) -> Rocket<Build> {
-        let db = Db::fetch(&rocket).unwrap().0.clone();
-
-        let fetch = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .unwrap();
-
-        let manager = EscalonJobsManager::new(Context { db, fetch });
+        let db = Db::fetch(&rocket)
+            .expect("ERROR: cron.init(); Db must be attached")
+            .0
+            .clone();
+        let config = rocket
+            .state::<SharedConfig>()
+            .expect("ERROR: cron.init(); AuthConfig must be managed")
+            .clone();
+        let udp_port = config.get().udp_port;
+
+        let fetch = rocket
+            .state::<Fetch>()
+            .expect("ERROR: cron.init(); Fetch must be managed")
+            .clone();
+
+        let manager = EscalonJobsManager::new(Context { db, fetch, config });
         let mut manager = manager
             .set_id(ConfigGetter::get_identity())
             .set_addr("0.0.0.0".parse::<IpAddr>().unwrap())
-            .set_port(ConfigGetter::get_udp_port().unwrap_or(65056))
+            .set_port(udp_port)
             .set_functions(Functions)
             .build()
             .await;
@@ -73,183 +73,98 @@ impl CronManager {
 }
 
 impl CronManager {
+    /// Schedules again the jobs this node owned before a restart and, when
+    /// no other node is newer, the ones left by nodes that are gone.
     pub async fn take_jobs_on_init(&self) {
-        let own_jobs = sqlx::query!(
-            r#"
-            SELECT cronjobs.id AS cron_id, escalonjobs.*
-            FROM cronjobs
-            INNER JOIN escalonjobs ON escalonjobs.id = cronjobs.job_id
-            WHERE cronjobs.owner = $1
-            AND escalonjobs.status != 'done'
-            AND escalonjobs.status != 'failed'
-            "#,
-            ConfigGetter::get_identity()
-        )
-        .fetch_all(&self.inner().context.db)
-        .await
-        .unwrap();
-
-        let own_jobs: Vec<(i32, EJob)> = own_jobs
-            .into_iter()
-            .map(|job| {
-                let id = job.cron_id;
-                let job = EJob {
-                    id: job.id,
-                    status: job.status,
-                    schedule: job.schedule,
-                    since: job.since.map(|d| d.into()),
-                    until: job.until.map(|d| d.into()),
-                };
-                (id, job)
-            })
-            .collect();
-
-        for job in own_jobs {
-            let old_uuid = job.1.id;
-            let new_ejob: NewEJob = job.1.into();
-            let escalon_job = self.inner().add_job(new_ejob).await;
-            let ejob: EJob = escalon_job.clone().into();
-
-            sqlx::query!(
-                r#"
-                INSERT INTO escalonjobs VALUES ($1, $2, $3, $4, $5)
-                "#,
-                ejob.id,
-                ejob.status,
-                ejob.schedule,
-                ejob.since,
-                ejob.until,
-            )
-            .execute(&self.inner().context.db)
-            .await
-            .unwrap();
-
-            sqlx::query!(
-                r#"
-                UPDATE cronjobs SET owner = $1, job_id = $2 WHERE id = $3
-                "#,
-                ConfigGetter::get_identity(),
-                escalon_job.job_id,
-                job.0,
-            )
-            .execute(&self.inner().context.db)
-            .await
-            .unwrap();
-
-            sqlx::query!(
-                r#"
-                DELETE FROM escalonjobs WHERE id = $1
-                "#,
-                old_uuid,
-            )
-            .execute(&self.inner().context.db)
-            .await
-            .unwrap();
+        let identity = ConfigGetter::get_identity();
+        let manager = self.inner();
+        let db = &manager.context.db;
+
+        match repository::pending(db, &identity, true).await {
+            Ok(jobs) => {
+                for (cronjob_id, job) in jobs {
+                    if let Err(e) = adopt(manager, cronjob_id, job).await {
+                        error!("CRON: job {cronjob_id} could not be scheduled; {e}");
+                    }
+                }
+            }
+            Err(e) => error!("CRON: own jobs could not be read; {e}"),
+        }
+
+        let other_jobs = match repository::pending(db, &identity, false).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("CRON: jobs of other nodes could not be read; {e}");
+                return;
+            }
+        };
+
+        if other_jobs.is_empty() {
+            return;
         }
 
-        let other_jobs = sqlx::query!(
-            r#"
-            SELECT cronjobs.id AS cron_id, escalonjobs.*
-            FROM cronjobs
-            INNER JOIN escalonjobs ON escalonjobs.id = cronjobs.job_id
-            WHERE cronjobs.owner != $1
-            AND escalonjobs.status != 'done'
-            AND escalonjobs.status != 'failed'
-            "#,
-            ConfigGetter::get_identity()
-        )
-        .fetch_all(&self.inner().context.db)
-        .await
-        .unwrap();
-
-        let other_jobs: Vec<(i32, EJob)> = other_jobs
-            .into_iter()
-            .map(|job| {
-                let id = job.cron_id;
-                let job = EJob {
-                    id: job.id,
-                    status: job.status,
-                    schedule: job.schedule,
-                    since: job.since.map(|d| d.into()),
-                    until: job.until.map(|d| d.into()),
-                };
-                (id, job)
-            })
-            .collect();
-
-        if !other_jobs.is_empty() {
-            let jobs = other_jobs.clone();
-            let manager = self.inner().clone();
-
-            rocket::tokio::spawn(async move {
-                rocket::tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-
-                let clients = manager.clients.clone().unwrap();
-                let own_start_time = manager.start_time.clone().unwrap();
-
-                if clients
+        let manager = manager.clone();
+        rocket::tokio::spawn(async move {
+            rocket::tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+
+            let (Some(clients), Some(own_start_time)) =
+                (manager.clients.clone(), manager.start_time.clone())
+            else {
+                return;
+            };
+
+            // A newer node takes them over
+            if clients
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .values()
+                .any(|client| client.start_time.duration_since(own_start_time).is_ok())
+            {
+                return;
+            }
+
+            for (cronjob_id, job) in other_jobs {
+                let running = clients
                     .lock()
-                    .unwrap()
-                    .iter()
-                    .find(|client| {
-                        let client_time = client.1.start_time;
-                        match client_time.duration_since(own_start_time) {
-                            Ok(_) => true,
-                            Err(_) => false,
-                        }
-                    })
-                    .is_some()
-                {
-                    return;
+                    .unwrap_or_else(|e| e.into_inner())
+                    .contains_key(&job.id.to_string());
+                if running {
+                    continue;
                 }
 
-                for job in jobs {
-                    if !clients.lock().unwrap().contains_key(&job.1.id.to_string()) {
-                        let old_uuid = job.1.id;
-                        let new_ejob: NewEJob = job.1.into();
-                        let escalon_job = manager.add_job(new_ejob).await;
-                        let ejob: EJob = escalon_job.clone().into();
-
-                        sqlx::query!(
-                            r#"
-                            INSERT INTO escalonjobs VALUES ($1, $2, $3, $4, $5)
-                            "#,
-                            ejob.id,
-                            ejob.status,
-                            ejob.schedule,
-                            ejob.since,
-                            ejob.until,
-                        )
-                        .execute(&manager.context.db)
-                        .await
-                        .unwrap();
-
-                        sqlx::query!(
-                            r#"
-                            UPDATE cronjobs SET owner = $1, job_id = $2 WHERE id = $3
-                            "#,
-                            ConfigGetter::get_identity(),
-                            escalon_job.job_id,
-                            job.0,
-                        )
-                        .execute(&manager.context.db)
-                        .await
-                        .unwrap();
-
-                        sqlx::query!(
-                            r#"
-                            DELETE FROM escalonjobs WHERE id = $1
-                            "#,
-                            old_uuid,
-                        )
-                        .execute(&manager.context.db)
-                        .await
-                        .unwrap();
-                    }
+                if let Err(e) = adopt(&manager, cronjob_id, job).await {
+                    error!("CRON: job {cronjob_id} could not be scheduled; {e}");
                 }
+            }
+        });
+    }
+}
 
-                return;
-            });
+/// Schedules `job` of the cron job `cronjob_id` on this node, points the
+/// cron job at it and makes up for the ticks it missed.
+async fn adopt(
+    manager: &EscalonJobsManager<Context>,
+    cronjob_id: i32,
+    job: EJob,
+) -> Result<(), String> {
+    let new_ejob: NewEJob = job.into();
+    let escalon_job = manager.add_job(new_ejob.clone()).await;
+    let ejob = EJob::scheduled(escalon_job, new_ejob);
+
+    let identity = ConfigGetter::get_identity();
+    match repository::reschedule(&manager.context.db, cronjob_id, &identity, &ejob).await {
+        Ok(Some(_)) => {
+            rocket::tokio::spawn(misfire::recover(manager.context.clone(), cronjob_id));
+            Ok(())
+        }
+        // Deleted meanwhile
+        Ok(None) => {
+            manager.remove_job(ejob.id).await;
+            Ok(())
+        }
+        Err(e) => {
+            manager.remove_job(ejob.id).await;
+            Err(e)
         }
     }
 }
@@ -265,78 +180,22 @@ impl EscalonJobsManagerTrait<Context> for Functions {
         start_at: usize,
         n_jobs: usize,
     ) -> Result<Vec<String>, ()> {
-        let jobs = sqlx::query!(
-            r#"
-            SELECT cronjobs.id AS cron_id, escalonjobs.*
-            FROM cronjobs
-            INNER JOIN escalonjobs ON escalonjobs.id = cronjobs.job_id
-            WHERE cronjobs.owner = $1
-            LIMIT $2
-            OFFSET $3
-            "#,
-            from_client,
-            n_jobs as i64,
-            start_at as i64,
-        )
-        .fetch_all(&manager.context.db)
-        .await
-        .unwrap();
-
-        let jobs: Vec<(i32, EJob)> = jobs
-            .into_iter()
-            .map(|job| {
-                let id = job.cron_id;
-                let job = EJob {
-                    id: job.id,
-                    status: job.status,
-                    schedule: job.schedule,
-                    since: job.since.map(|d| d.into()),
-                    until: job.until.map(|d| d.into()),
-                };
-                (id, job)
-            })
-            .collect();
-
-        let mut response = Vec::new();
-        for job in jobs {
-            let id = job.0;
-            let job = job.1;
-            let old_uuid = job.id;
-
-            let new_ejob: NewEJob = job.into();
-            let escalon_job = manager.add_job(new_ejob).await;
-            let ejob: EJob = escalon_job.clone().into();
-
-            sqlx::query!(
-                r#"
-                INSERT INTO escalonjobs VALUES ($1, $2, $3, $4, $5)
-                "#,
-                ejob.id,
-                ejob.status,
-                ejob.schedule,
-                ejob.since,
-                ejob.until,
-            )
-            .execute(&manager.context.db)
+        let db = &manager.context.db;
+        let jobs = repository::owned_by(db, &from_client, start_at as i64, n_jobs as i64)
             .await
-            .unwrap();
-
-            sqlx::query!(
-                r#"
-                UPDATE cronjobs SET owner = $1, job_id = $2 WHERE id = $3
-                "#,
-                ConfigGetter::get_identity(),
-                escalon_job.job_id,
-                id,
-            )
-            .execute(&manager.context.db)
-            .await
-            .unwrap();
+            .map_err(|e| error!("CRON: jobs of {from_client} could not be read; {e}"))?;
+
+        let mut taken = Vec::new();
+        for (cronjob_id, job) in jobs {
+            let previous = job.id;
 
-            response.push(old_uuid.to_string());
+            match adopt(manager, cronjob_id, job).await {
+                Ok(()) => taken.push(previous.to_string()),
+                Err(e) => error!("CRON: job {cronjob_id} could not be taken over; {e}"),
+            }
         }
 
-        Ok(response)
+        Ok(taken)
     }
 
     async fn drop_jobs(
@@ -344,25 +203,18 @@ impl EscalonJobsManagerTrait<Context> for Functions {
         manager: &EscalonJobsManager<Context>,
         jobs: Vec<String>,
     ) -> Result<(), ()> {
-        let mut _affected_rows: usize = 0;
-
-        for ejob_id in jobs {
-            let ejob_id = Uuid::parse_str(ejob_id.as_str()).unwrap();
-
-            let rows = sqlx::query!(
-                r#"
-                DELETE FROM escalonjobs WHERE id = $1
-                "#,
-                ejob_id,
-            )
-            .execute(&manager.context.db)
-            .await
-            .unwrap();
-
-            _affected_rows += rows.rows_affected() as usize;
+        for job_id in jobs {
+            let Ok(job_id) = Uuid::parse_str(&job_id) else {
+                warn!("CRON: {job_id} is not a job id");
+                continue;
+            };
+
+            if let Err(e) = repository::drop_job(&manager.context.db, job_id).await {
+                error!("CRON: job {job_id} could not be dropped; {e}");
+            }
 
-            if (manager.get_job(ejob_id).await).is_some() {
-                manager.remove_job(ejob_id).await;
+            if manager.get_job(job_id).await.is_some() {
+                manager.remove_job(job_id).await;
             }
         }
 
@@ -1,29 +1,114 @@
 #[cfg(feature = "fetch")]
-use super::claims::{Claims, UserInClaims};
+use super::claims::{Claims, EncodeClaims, UserInClaims};
 #[cfg(feature = "fetch")]
-use rocket::tokio::sync::Mutex;
+use crate::app::providers::config::AuthConfig;
+#[cfg(feature = "fetch")]
+use rocket::{Build, Rocket};
+#[cfg(feature = "fetch")]
+use serde::Deserialize;
+#[cfg(feature = "fetch")]
+use std::collections::HashMap;
 #[cfg(feature = "fetch")]
 use std::sync::Arc;
+#[cfg(feature = "fetch")]
+use std::time::Duration;
 
+/// Tuning of the outbound http client, read from the `fetch` table of
+/// Rocket.toml. Durations are in seconds.
 #[cfg(feature = "fetch")]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct FetchConfig {
+    pub timeout: u64,
+    pub connect_timeout: u64,
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: u64,
+    pub keep_alive: u64,
+    pub http2: bool,
+    pub timeouts: HashMap<String, u64>,
+}
+
+#[cfg(feature = "fetch")]
+impl Default for FetchConfig {
+    fn default() -> Self {
+        FetchConfig {
+            timeout: 10,
+            connect_timeout: 3,
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout: 90,
+            keep_alive: 60,
+            http2: false,
+            timeouts: HashMap::new(),
+        }
+    }
+}
+
+/// Shared outbound http client. `reqwest::Client` is already an `Arc` around
+/// a connection pool, so clones are cheap and requests run concurrently.
+#[cfg(feature = "fetch")]
+#[derive(Clone)]
 pub struct Fetch {
-    pub client: Arc<Mutex<reqwest::Client>>,
+    pub client: reqwest::Client,
+    timeouts: Arc<HashMap<String, Duration>>,
+    default_timeout: Duration,
 }
 
 #[cfg(feature = "fetch")]
 impl Fetch {
-    pub fn new() -> Self {
-        // let client = Arc::new(Mutex::new(reqwest::Client::new()));
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()
-            .unwrap();
-        Fetch {
-            client: Arc::new(Mutex::new(client)),
+    pub fn new(config: &FetchConfig) -> Result<Self, reqwest::Error> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .connect_timeout(Duration::from_secs(config.connect_timeout))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout))
+            .tcp_keepalive(Duration::from_secs(config.keep_alive));
+
+        if config.http2 {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        let timeouts = config
+            .timeouts
+            .iter()
+            .map(|(service, secs)| (service.clone(), Duration::from_secs(*secs)))
+            .collect();
+
+        Ok(Fetch {
+            client: builder.build()?,
+            timeouts: Arc::new(timeouts),
+            default_timeout: Duration::from_secs(config.timeout),
+        })
+    }
+
+    pub async fn init(rocket: Rocket<Build>) -> Result<Rocket<Build>, Rocket<Build>> {
+        let config = match rocket.figment().extract_inner::<FetchConfig>("fetch") {
+            Ok(config) => config,
+            Err(e) if e.missing() => FetchConfig::default(),
+            Err(e) => {
+                error!("ERROR: fetch.init(); invalid fetch config; {e}");
+                return Err(rocket);
+            }
+        };
+
+        match Fetch::new(&config) {
+            Ok(fetch) => Ok(rocket.manage(fetch)),
+            Err(e) => {
+                error!("ERROR: fetch.init(); http client; {e}");
+                Err(rocket)
+            }
         }
     }
 
-    pub async fn robot_token() -> Result<String, jsonwebtoken::errors::Error> {
-        return Claims::from(UserInClaims::default()).enconde_for_robot();
+    pub fn timeout_for(&self, service: &str) -> Duration {
+        self.timeouts
+            .get(service)
+            .copied()
+            .unwrap_or(self.default_timeout)
+    }
+
+    pub async fn robot_token(
+        config: &AuthConfig,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        Claims::from(UserInClaims::default()).enconde_for_robot(config)
     }
 }
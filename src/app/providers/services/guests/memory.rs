@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+
+use crate::app::providers::services::outbox::{Effect, OutboxStore};
+
+use super::{Guest, GuestRegistry};
+
+/// Guests created by this instance, for builds without a database.
+#[derive(Default)]
+pub struct MemoryGuestRegistry(RwLock<HashMap<i32, Guest>>);
+
+#[rocket::async_trait]
+impl GuestRegistry for MemoryGuestRegistry {
+    async fn register(&self, guest: Guest) -> Result<(), String> {
+        let mut guests = self.0.write().unwrap_or_else(|e| e.into_inner());
+        guests.insert(guest.user_id, guest);
+
+        Ok(())
+    }
+
+    async fn expired(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<Guest>, String> {
+        let guests = self.0.read().unwrap_or_else(|e| e.into_inner());
+
+        let mut expired = guests
+            .values()
+            .filter(|guest| guest.expires_at.is_some_and(|at| at <= now))
+            .cloned()
+            .collect::<Vec<_>>();
+        expired.sort_by_key(|guest| guest.expires_at);
+        expired.truncate(limit.max(0) as usize);
+
+        Ok(expired)
+    }
+
+    async fn purge(
+        &self,
+        user_id: i32,
+        effects: Vec<Effect>,
+        outbox: &dyn OutboxStore,
+    ) -> Result<(), String> {
+        outbox.enqueue(effects).await?;
+
+        let mut guests = self.0.write().unwrap_or_else(|e| e.into_inner());
+        guests.remove(&user_id);
+
+        Ok(())
+    }
+}
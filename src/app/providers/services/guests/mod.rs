@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rocket::fairing::AdHoc;
+use serde::Serialize;
+
+use crate::app::providers::services::outbox::{Effect, OutboxStore};
+
+pub mod memory;
+#[cfg(feature = "db_sqlx")]
+pub mod postgres;
+pub mod sweeper;
+
+pub use memory::MemoryGuestRegistry;
+#[cfg(feature = "db_sqlx")]
+pub use postgres::PostgresGuestRegistry;
+
+/// Row of the `guests` table, one per guest created by the onboarding.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "db_sqlx", derive(rocket_db_pools::sqlx::FromRow))]
+#[serde(crate = "rocket::serde")]
+pub struct Guest {
+    pub user_id: i32,
+    pub project_id: i32,
+    pub created_at: DateTime<Utc>,
+    /// `None` for projects without a guest lifetime.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Guests this service created and has not purged yet.
+#[rocket::async_trait]
+pub trait GuestRegistry: Send + Sync {
+    async fn register(&self, guest: Guest) -> Result<(), String>;
+    /// Oldest first, at most `limit` guests expired at `now`.
+    async fn expired(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<Guest>, String>;
+    /// Queues the purge `effects` in `outbox` and drops the guest, in one
+    /// transaction when both live in the database.
+    async fn purge(
+        &self,
+        user_id: i32,
+        effects: Vec<Effect>,
+        outbox: &dyn OutboxStore,
+    ) -> Result<(), String>;
+}
+
+/// Manages the `guests` table registry with `db_sqlx`, or an in memory one
+/// otherwise. Attach after the database fairings.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("Init guest registry", |rocket| async {
+        if rocket.state::<Arc<dyn GuestRegistry>>().is_some() {
+            return rocket;
+        }
+
+        #[cfg(feature = "db_sqlx")]
+        {
+            use rocket_db_pools::Database;
+
+            if let Some(db) = crate::database::connection::Db::fetch(&rocket) {
+                let registry: Arc<dyn GuestRegistry> =
+                    Arc::new(PostgresGuestRegistry::new(db.0.clone()));
+                return rocket.manage(registry);
+            }
+        }
+
+        info!("GUESTS: no database, guests created before a restart are never purged");
+        let registry: Arc<dyn GuestRegistry> = Arc::new(MemoryGuestRegistry::default());
+        rocket.manage(registry)
+    })
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use rocket_db_pools::sqlx::{self, PgPool};
+
+use crate::app::providers::services::outbox::{Effect, OutboxStore};
+
+use super::{Guest, GuestRegistry};
+
+/// The `guests` table.
+pub struct PostgresGuestRegistry(PgPool);
+
+impl PostgresGuestRegistry {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresGuestRegistry(pool)
+    }
+}
+
+#[rocket::async_trait]
+impl GuestRegistry for PostgresGuestRegistry {
+    async fn register(&self, guest: Guest) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO guests (user_id, project_id, created_at, expires_at) \
+             VALUES ($1, $2, $3, $4) ON CONFLICT (user_id) \
+             DO UPDATE SET project_id = $2, created_at = $3, expires_at = $4",
+        )
+        .bind(guest.user_id)
+        .bind(guest.project_id)
+        .bind(guest.created_at)
+        .bind(guest.expires_at)
+        .execute(&self.0)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+
+    async fn expired(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<Guest>, String> {
+        sqlx::query_as::<_, Guest>(
+            "SELECT user_id, project_id, created_at, expires_at FROM guests \
+             WHERE expires_at <= $1 ORDER BY expires_at LIMIT $2",
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&self.0)
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    async fn purge(
+        &self,
+        user_id: i32,
+        effects: Vec<Effect>,
+        outbox: &dyn OutboxStore,
+    ) -> Result<(), String> {
+        let mut tx = self.0.begin().await.map_err(|e| e.to_string())?;
+
+        outbox.enqueue_in(&mut tx, effects).await?;
+        sqlx::query("DELETE FROM guests WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::Utc;
+use rocket::fairing::AdHoc;
+use serde::{Deserialize, Serialize};
+
+use crate::app::providers::services::outbox::{Effect, Outbox};
+
+use super::{Guest, GuestRegistry};
+
+/// What happens to an expired guest. `Anonymize` keeps the user, and the
+/// records it left in its project, but drops its push tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum PurgeMode {
+    Delete,
+    Anonymize,
+}
+
+impl PurgeMode {
+    fn effects(self, guest: &Guest) -> Vec<Effect> {
+        let user_id = guest.user_id;
+
+        match self {
+            PurgeMode::Delete => vec![
+                Effect::DeleteMessagingToken { user_id },
+                Effect::RemoveMembership {
+                    project_id: guest.project_id,
+                    user_id,
+                },
+                Effect::DeleteUser { user_id },
+            ],
+            PurgeMode::Anonymize => vec![
+                Effect::DeleteMessagingToken { user_id },
+                Effect::AnonymizeUser { user_id },
+            ],
+        }
+    }
+}
+
+/// The `guest_sweeper` table of Rocket.toml. An empty `schedule` leaves the
+/// sweep to `POST /admin/guests/sweep`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct SweeperConfig {
+    pub mode: PurgeMode,
+    pub batch: i64,
+    pub schedule: String,
+}
+
+impl Default for SweeperConfig {
+    fn default() -> Self {
+        SweeperConfig {
+            mode: PurgeMode::Delete,
+            batch: 500,
+            schedule: "0 0 3 * * *".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SweepReport {
+    pub dry_run: bool,
+    pub mode: PurgeMode,
+    pub guests: Vec<Guest>,
+}
+
+/// Queues the purge of up to `batch` expired guests in the outbox, or only
+/// lists them for a dry run.
+pub async fn sweep(
+    registry: &dyn GuestRegistry,
+    outbox: &Outbox,
+    config: &SweeperConfig,
+    dry_run: bool,
+) -> Result<SweepReport, String> {
+    let guests = registry.expired(Utc::now(), config.batch).await?;
+
+    if !dry_run {
+        for guest in &guests {
+            registry
+                .purge(guest.user_id, config.mode.effects(guest), outbox.store())
+                .await?;
+        }
+        info!("GUESTS: {} expired guests queued for purge", guests.len());
+    }
+
+    Ok(SweepReport {
+        dry_run,
+        mode: config.mode,
+        guests,
+    })
+}
+
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Init guest sweeper", |rocket| async {
+        match rocket
+            .figment()
+            .extract_inner::<SweeperConfig>("guest_sweeper")
+        {
+            Ok(config) => Ok(rocket.manage(config)),
+            Err(e) if e.missing() => Ok(rocket.manage(SweeperConfig::default())),
+            Err(e) => {
+                error!("GUESTS: invalid guest_sweeper config; {e}");
+                Err(rocket)
+            }
+        }
+    })
+}
+
+/// Runs the sweep on `schedule` with the cron manager. Every node schedules
+/// it; a guest is dropped from the registry once queued, so a concurrent
+/// sweep at most queues removals that find nothing left.
+#[cfg(feature = "cron")]
+pub fn schedule() -> AdHoc {
+    use std::sync::Arc;
+
+    use crate::app::providers::services::cron::CronManager;
+
+    AdHoc::on_liftoff("Schedule guest sweeper", |rocket| {
+        Box::pin(async move {
+            let job = match (
+                rocket.state::<Arc<dyn GuestRegistry>>(),
+                rocket.state::<Outbox>(),
+                rocket.state::<SweeperConfig>(),
+            ) {
+                (Some(registry), Some(outbox), Some(config)) if !config.schedule.is_empty() => {
+                    SweepJob {
+                        registry: registry.clone(),
+                        outbox: outbox.clone(),
+                        config: config.clone(),
+                    }
+                }
+                _ => return,
+            };
+
+            match rocket.state::<CronManager>() {
+                Some(cron) => {
+                    cron.inner().add_job(job).await;
+                }
+                None => {
+                    warn!("GUESTS: no cron manager, expired guests are only purged on demand")
+                }
+            }
+        })
+    })
+}
+
+#[cfg(feature = "cron")]
+#[derive(Clone)]
+struct SweepJob {
+    registry: std::sync::Arc<dyn GuestRegistry>,
+    outbox: Outbox,
+    config: SweeperConfig,
+}
+
+#[cfg(feature = "cron")]
+impl From<SweepJob> for escalon_jobs::NewEscalonJob {
+    fn from(job: SweepJob) -> Self {
+        escalon_jobs::NewEscalonJob {
+            schedule: job.config.schedule,
+            since: None,
+            until: None,
+        }
+    }
+}
+
+#[cfg(feature = "cron")]
+#[rocket::async_trait]
+impl escalon_jobs::EscalonJobTrait<crate::app::providers::services::cron::Context>
+    for SweepJob
+{
+    async fn run_job(
+        &self,
+        job: escalon_jobs::EscalonJob,
+        _context: crate::app::providers::services::cron::Context,
+    ) -> escalon_jobs::EscalonJob {
+        if let Err(e) = sweep(self.registry.as_ref(), &self.outbox, &self.config, false).await {
+            error!("GUESTS: sweep failed; {e}");
+        }
+
+        job
+    }
+}
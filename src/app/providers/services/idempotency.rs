@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use ring::digest::{digest, SHA256};
+
+use crate::app::providers::errors::AuthError;
+
+/// Keys are remembered for a day unless `idempotency_ttl` says otherwise.
+pub const DEFAULT_TTL: i64 = 86400;
+
+#[derive(Debug, Clone)]
+enum Entry {
+    /// The first request is still running.
+    Pending {
+        fingerprint: String,
+        expires_at: i64,
+    },
+    /// Its successful outcome, serialized.
+    Done {
+        fingerprint: String,
+        outcome: String,
+        expires_at: i64,
+    },
+}
+
+impl Entry {
+    fn expires_at(&self) -> i64 {
+        match self {
+            Entry::Pending { expires_at, .. } | Entry::Done { expires_at, .. } => *expires_at,
+        }
+    }
+
+    fn fingerprint(&self) -> &str {
+        match self {
+            Entry::Pending { fingerprint, .. } | Entry::Done { fingerprint, .. } => fingerprint,
+        }
+    }
+}
+
+pub enum Claim {
+    /// First time the key is seen, the caller runs the request.
+    Fresh,
+    Replay(String),
+}
+
+/// `Idempotency-Key`s seen by this instance. Only successes are kept, a
+/// failed request can be retried with the same key.
+///
+/// Entries live in memory, so they are lost on restart and each replica has
+/// its own: a retry that lands on another replica runs again. Outcomes are
+/// kept for the whole ttl, never store credentials in them.
+#[derive(Clone)]
+pub struct Idempotency {
+    entries: Arc<RwLock<HashMap<String, Entry>>>,
+    ttl: i64,
+}
+
+impl Default for Idempotency {
+    fn default() -> Self {
+        Idempotency::new(DEFAULT_TTL)
+    }
+}
+
+impl Idempotency {
+    pub fn new(ttl: i64) -> Self {
+        Idempotency {
+            entries: Arc::default(),
+            ttl,
+        }
+    }
+
+    /// Hash of what makes two requests the same one.
+    pub fn fingerprint(parts: &[&[u8]]) -> String {
+        let joined = parts.join(&0u8);
+
+        digest(&SHA256, &joined)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn claim(&self, key: &str, fingerprint: &str) -> Result<Claim, AuthError> {
+        let now = chrono::Utc::now().timestamp();
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| entry.expires_at() > now);
+
+        match entries.get(key) {
+            Some(entry) if entry.fingerprint() != fingerprint => {
+                Err(AuthError::IdempotencyKeyReused)
+            }
+            Some(Entry::Pending { .. }) => Err(AuthError::IdempotencyInFlight),
+            Some(Entry::Done { outcome, .. }) => Ok(Claim::Replay(outcome.clone())),
+            None => {
+                entries.insert(
+                    key.to_string(),
+                    Entry::Pending {
+                        fingerprint: fingerprint.to_string(),
+                        expires_at: now + self.ttl,
+                    },
+                );
+                Ok(Claim::Fresh)
+            }
+        }
+    }
+
+    pub fn complete(&self, key: &str, outcome: String) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(Entry::Pending {
+            fingerprint,
+            expires_at,
+        }) = entries.remove(key)
+        {
+            entries.insert(
+                key.to_string(),
+                Entry::Done {
+                    fingerprint,
+                    outcome,
+                    expires_at,
+                },
+            );
+        }
+    }
+
+    pub fn release(&self, key: &str) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(Entry::Pending { .. }) = entries.get(key) {
+            entries.remove(key);
+        }
+    }
+}
@@ -1,5 +1,17 @@
+pub mod audit;
 pub mod claims;
+#[cfg(feature = "fetch")]
+pub mod clients;
+pub mod cookie;
 #[cfg(feature = "cron")]
 pub mod cron;
 pub mod fetch;
+pub mod guests;
+pub mod idempotency;
+pub mod outbox;
+pub mod reload;
+pub mod revocation;
+#[cfg(feature = "cron")]
+pub mod schedule;
 pub mod token;
+pub mod upstream;
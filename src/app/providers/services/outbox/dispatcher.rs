@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use rocket::fairing::AdHoc;
+use rocket::serde::json::Value;
+
+use crate::app::providers::services::clients::{MessagingClient, ProjectClient, UserClient};
+use crate::app::providers::services::fetch::Fetch;
+use crate::app::providers::services::upstream::{self, UpstreamErrorKind};
+
+use super::{Effect, Outbox, OutboxMessage};
+
+/// Delivers the outbox messages that are due.
+pub struct Dispatcher {
+    outbox: Outbox,
+    fetch: Fetch,
+    users: Arc<dyn UserClient>,
+    projects: Arc<dyn ProjectClient>,
+    messaging: Arc<dyn MessagingClient>,
+}
+
+impl Dispatcher {
+    pub fn new(
+        outbox: Outbox,
+        fetch: Fetch,
+        users: Arc<dyn UserClient>,
+        projects: Arc<dyn ProjectClient>,
+        messaging: Arc<dyn MessagingClient>,
+    ) -> Self {
+        Dispatcher {
+            outbox,
+            fetch,
+            users,
+            projects,
+            messaging,
+        }
+    }
+
+    /// Runs one batch and returns how many messages were delivered.
+    pub async fn run_once(&self) -> Result<usize, String> {
+        let config = self.outbox.config();
+        let store = self.outbox.store();
+        let messages = store
+            .claim(config.batch, Duration::from_secs(config.lease))
+            .await?;
+
+        let mut delivered = 0;
+        for message in messages {
+            match self.deliver(&message.effect).await {
+                Ok(()) => {
+                    store.delivered(message.id).await?;
+                    delivered += 1;
+                }
+                Err(e) => self.failed(&message, e).await?,
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    async fn failed(&self, message: &OutboxMessage, error: String) -> Result<(), String> {
+        let config = self.outbox.config();
+
+        let retry_at = match message.attempts < config.max_attempts {
+            true => {
+                let backoff = config.backoff_for(message.attempts);
+                warn!(
+                    "OUTBOX: message {} failed, retry in {backoff:?}; {error}",
+                    message.id
+                );
+                Some(
+                    Utc::now()
+                        + chrono::Duration::from_std(backoff).map_err(|e| e.to_string())?,
+                )
+            }
+            false => {
+                error!(
+                    "OUTBOX: message {} is dead after {} attempts; {error}",
+                    message.id, message.attempts
+                );
+                None
+            }
+        };
+
+        self.outbox
+            .store()
+            .failed(message.id, error, retry_at)
+            .await
+    }
+
+    async fn deliver(&self, effect: &Effect) -> Result<(), String> {
+        let result = match effect {
+            Effect::ResetTokens { user_id } => self.messaging.reset_tokens(*user_id).await,
+            Effect::DeleteMessagingToken { user_id } => {
+                self.messaging.delete_user(*user_id).await
+            }
+            Effect::RemoveMembership {
+                project_id,
+                user_id,
+            } => self.projects.remove_user(*project_id, *user_id).await,
+            Effect::DeleteUser { user_id } => self.users.delete(*user_id).await,
+            Effect::AnonymizeUser { user_id } => self.users.anonymize(*user_id).await,
+            Effect::Publish { sink, body } => return self.publish(sink, body).await,
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if effect.is_removal() && e.kind == UpstreamErrorKind::Status(404) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn publish(&self, sink: &str, body: &Value) -> Result<(), String> {
+        let url = self
+            .outbox
+            .config()
+            .sinks
+            .get(sink)
+            .ok_or_else(|| format!("sink `{sink}` is not configured"))?;
+
+        let res = self
+            .fetch
+            .client
+            .post(url)
+            .timeout(self.fetch.timeout_for("outbox"))
+            .json(body)
+            .send()
+            .await;
+
+        upstream::expect_success("outbox", res)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{sink}: {e}"))
+    }
+}
+
+/// Polls the outbox every `poll_interval` seconds once launched.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Outbox dispatcher", |rocket| {
+        Box::pin(async move {
+            let dispatcher = match (
+                rocket.state::<Outbox>(),
+                rocket.state::<Fetch>(),
+                rocket.state::<Arc<dyn UserClient>>(),
+                rocket.state::<Arc<dyn ProjectClient>>(),
+                rocket.state::<Arc<dyn MessagingClient>>(),
+            ) {
+                (Some(outbox), Some(fetch), Some(users), Some(projects), Some(messaging)) => {
+                    Dispatcher::new(
+                        outbox.clone(),
+                        fetch.clone(),
+                        users.clone(),
+                        projects.clone(),
+                        messaging.clone(),
+                    )
+                }
+                _ => {
+                    error!("OUTBOX: the dispatcher needs the outbox and the service clients");
+                    return;
+                }
+            };
+
+            let interval = Duration::from_secs(dispatcher.outbox.config().poll_interval.max(1));
+            rocket::tokio::spawn(async move {
+                loop {
+                    rocket::tokio::time::sleep(interval).await;
+                    if let Err(e) = dispatcher.run_once().await {
+                        error!("OUTBOX: dispatch failed; {e}");
+                    }
+                }
+            });
+        })
+    })
+}
@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use super::{Effect, OutboxMessage, OutboxStore};
+
+/// Outbox of this instance, for builds without a database.
+#[derive(Default)]
+pub struct MemoryOutboxStore {
+    messages: Mutex<Vec<OutboxMessage>>,
+    next_id: Mutex<i64>,
+}
+
+#[rocket::async_trait]
+impl OutboxStore for MemoryOutboxStore {
+    async fn enqueue(&self, effects: Vec<Effect>) -> Result<(), String> {
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+        let mut next_id = self.next_id.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+
+        for effect in effects {
+            *next_id += 1;
+            messages.push(OutboxMessage {
+                id: *next_id,
+                effect,
+                attempts: 0,
+                created_at: now,
+                available_at: now,
+                last_error: None,
+                dead_at: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn claim(&self, limit: i64, lease: Duration) -> Result<Vec<OutboxMessage>, String> {
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+        let leased_until =
+            now + chrono::Duration::from_std(lease).map_err(|e| e.to_string())?;
+
+        Ok(messages
+            .iter_mut()
+            .filter(|message| message.dead_at.is_none() && message.available_at <= now)
+            .take(limit.max(0) as usize)
+            .map(|message| {
+                message.attempts += 1;
+                message.available_at = leased_until;
+                message.clone()
+            })
+            .collect())
+    }
+
+    async fn delivered(&self, id: i64) -> Result<(), String> {
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+        messages.retain(|message| message.id != id);
+
+        Ok(())
+    }
+
+    async fn failed(
+        &self,
+        id: i64,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), String> {
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(message) = messages.iter_mut().find(|message| message.id == id) {
+            message.last_error = Some(error);
+            match retry_at {
+                Some(retry_at) => message.available_at = retry_at,
+                None => message.dead_at = Some(Utc::now()),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn dead_letters(&self, limit: i64) -> Result<Vec<OutboxMessage>, String> {
+        let messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+
+        Ok(messages
+            .iter()
+            .rev()
+            .filter(|message| message.dead_at.is_some())
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn requeue(&self, id: i64) -> Result<bool, String> {
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+
+        match messages
+            .iter_mut()
+            .find(|message| message.id == id && message.dead_at.is_some())
+        {
+            Some(message) => {
+                message.attempts = 0;
+                message.dead_at = None;
+                message.available_at = Utc::now();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rocket::fairing::AdHoc;
+use rocket::serde::json::Value;
+#[cfg(feature = "db_sqlx")]
+use rocket_db_pools::sqlx::{Postgres, Transaction};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "fetch")]
+pub mod dispatcher;
+pub mod memory;
+#[cfg(feature = "db_sqlx")]
+pub mod postgres;
+
+pub use memory::MemoryOutboxStore;
+#[cfg(feature = "db_sqlx")]
+pub use postgres::PostgresOutboxStore;
+
+/// Side effect of an auth state change, delivered later by the dispatcher.
+/// Removals treat a 404 as already done, so every effect can be retried.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    /// Drops the push tokens of a user that logged out.
+    ResetTokens {
+        user_id: i32,
+    },
+    /// Undo steps of an onboarding that could not be compensated inline.
+    DeleteMessagingToken {
+        user_id: i32,
+    },
+    RemoveMembership {
+        project_id: i32,
+        user_id: i32,
+    },
+    DeleteUser {
+        user_id: i32,
+    },
+    /// Purge of an expired guest whose records are kept.
+    AnonymizeUser {
+        user_id: i32,
+    },
+    /// Posts `body` to the url of `sink` in the `outbox.sinks` table.
+    Publish {
+        sink: String,
+        body: Value,
+    },
+}
+
+impl Effect {
+    pub fn is_removal(&self) -> bool {
+        !matches!(self, Effect::Publish { .. })
+    }
+}
+
+/// Row of the `outbox` table. `dead_at` is set once it ran out of attempts.
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OutboxMessage {
+    pub id: i64,
+    pub effect: Effect,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub available_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub dead_at: Option<DateTime<Utc>>,
+}
+
+#[rocket::async_trait]
+pub trait OutboxStore: Send + Sync {
+    /// Stores all the effects of one state change at once.
+    async fn enqueue(&self, effects: Vec<Effect>) -> Result<(), String>;
+    /// `enqueue` inside `tx`, the transaction of the state change. Stores
+    /// outside the database write on their own.
+    #[cfg(feature = "db_sqlx")]
+    async fn enqueue_in(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        effects: Vec<Effect>,
+    ) -> Result<(), String> {
+        let _ = tx;
+        self.enqueue(effects).await
+    }
+    /// Up to `limit` due messages, hidden from other dispatchers for `lease`.
+    /// Claiming counts as an attempt.
+    async fn claim(&self, limit: i64, lease: Duration) -> Result<Vec<OutboxMessage>, String>;
+    async fn delivered(&self, id: i64) -> Result<(), String>;
+    /// Schedules another attempt at `retry_at`, or dead letters the message
+    /// for `None`.
+    async fn failed(
+        &self,
+        id: i64,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), String>;
+    /// Newest first, at most `limit` messages.
+    async fn dead_letters(&self, limit: i64) -> Result<Vec<OutboxMessage>, String>;
+    /// Puts a dead letter back in the queue with its attempts reset. `false`
+    /// when there is no such dead letter.
+    async fn requeue(&self, id: i64) -> Result<bool, String>;
+}
+
+/// Tuning of the dispatcher, read from the `outbox` table of Rocket.toml.
+/// Durations are in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct OutboxConfig {
+    pub poll_interval: u64,
+    pub batch: i64,
+    pub lease: u64,
+    pub max_attempts: i32,
+    pub backoff: u64,
+    pub max_backoff: u64,
+    pub sinks: HashMap<String, String>,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        OutboxConfig {
+            poll_interval: 5,
+            batch: 50,
+            lease: 60,
+            max_attempts: 10,
+            backoff: 2,
+            max_backoff: 3600,
+            sinks: HashMap::new(),
+        }
+    }
+}
+
+impl OutboxConfig {
+    /// Exponential delay before the attempt after `attempts` failed ones.
+    pub fn backoff_for(&self, attempts: i32) -> Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 31) as u32;
+        let secs = self.backoff.saturating_mul(2u64.pow(exponent));
+
+        Duration::from_secs(secs.min(self.max_backoff))
+    }
+}
+
+/// Managed handle to the outbox.
+#[derive(Clone)]
+pub struct Outbox {
+    store: Arc<dyn OutboxStore>,
+    config: Arc<OutboxConfig>,
+}
+
+impl Outbox {
+    pub fn new(store: Arc<dyn OutboxStore>, config: OutboxConfig) -> Self {
+        Outbox {
+            store,
+            config: Arc::new(config),
+        }
+    }
+
+    pub fn store(&self) -> &dyn OutboxStore {
+        self.store.as_ref()
+    }
+
+    pub fn config(&self) -> &OutboxConfig {
+        &self.config
+    }
+
+    /// Enqueues `effects`; a failing store is logged, the state change
+    /// already happened.
+    pub async fn send(&self, effects: Vec<Effect>) {
+        if let Err(e) = self.store.enqueue(effects.clone()).await {
+            error!("OUTBOX: {effects:?} could not be stored; {e}");
+        }
+    }
+
+    /// Enqueues `body` for `sink`, nothing when the sink is not configured.
+    pub async fn publish(&self, sink: &str, body: Value) {
+        if let Some(effect) = self.publication(sink, body) {
+            self.send(vec![effect]).await;
+        }
+    }
+
+    /// The effect posting `body` to `sink`, `None` when the sink is not
+    /// configured.
+    pub fn publication(&self, sink: &str, body: Value) -> Option<Effect> {
+        self.config
+            .sinks
+            .contains_key(sink)
+            .then(|| Effect::Publish {
+                sink: sink.to_string(),
+                body,
+            })
+    }
+}
+
+/// Manages the `outbox` table store with `db_sqlx`, or an in memory one
+/// otherwise. Attach after the database fairings.
+pub fn fairing() -> AdHoc {
+    AdHoc::try_on_ignite("Init outbox", |rocket| async {
+        if rocket.state::<Outbox>().is_some() {
+            return Ok(rocket);
+        }
+
+        let config = match rocket.figment().extract_inner::<OutboxConfig>("outbox") {
+            Ok(config) => config,
+            Err(e) if e.missing() => OutboxConfig::default(),
+            Err(e) => {
+                error!("OUTBOX: invalid outbox config; {e}");
+                return Err(rocket);
+            }
+        };
+
+        #[cfg(feature = "db_sqlx")]
+        {
+            use rocket_db_pools::Database;
+
+            if let Some(db) = crate::database::connection::Db::fetch(&rocket) {
+                let store = Arc::new(PostgresOutboxStore::new(db.0.clone()));
+                return Ok(rocket.manage(Outbox::new(store, config)));
+            }
+        }
+
+        info!("OUTBOX: no database, pending effects are lost on restart");
+        let store = Arc::new(MemoryOutboxStore::default());
+        Ok(rocket.manage(Outbox::new(store, config)))
+    })
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rocket_db_pools::sqlx::{self, types::Json, PgPool, Postgres, Transaction};
+
+use super::{Effect, OutboxMessage, OutboxStore};
+
+const COLUMNS: &str = "id, effect, attempts, created_at, available_at, last_error, dead_at";
+
+#[derive(sqlx::FromRow)]
+struct Row {
+    id: i64,
+    effect: Json<Effect>,
+    attempts: i32,
+    created_at: DateTime<Utc>,
+    available_at: DateTime<Utc>,
+    last_error: Option<String>,
+    dead_at: Option<DateTime<Utc>>,
+}
+
+impl From<Row> for OutboxMessage {
+    fn from(row: Row) -> Self {
+        OutboxMessage {
+            id: row.id,
+            effect: row.effect.0,
+            attempts: row.attempts,
+            created_at: row.created_at,
+            available_at: row.available_at,
+            last_error: row.last_error,
+            dead_at: row.dead_at,
+        }
+    }
+}
+
+/// The `outbox` table. Several instances can dispatch it, claims skip the
+/// rows another one has locked.
+pub struct PostgresOutboxStore(PgPool);
+
+impl PostgresOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresOutboxStore(pool)
+    }
+}
+
+const ENQUEUE: &str = "INSERT INTO outbox (effect) SELECT * FROM UNNEST($1::jsonb[])";
+
+#[rocket::async_trait]
+impl OutboxStore for PostgresOutboxStore {
+    async fn enqueue(&self, effects: Vec<Effect>) -> Result<(), String> {
+        let effects = effects.into_iter().map(Json).collect::<Vec<_>>();
+
+        sqlx::query(ENQUEUE)
+            .bind(effects)
+            .execute(&self.0)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn enqueue_in(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        effects: Vec<Effect>,
+    ) -> Result<(), String> {
+        let effects = effects.into_iter().map(Json).collect::<Vec<_>>();
+
+        sqlx::query(ENQUEUE)
+            .bind(effects)
+            .execute(&mut **tx)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn claim(&self, limit: i64, lease: Duration) -> Result<Vec<OutboxMessage>, String> {
+        let leased_until =
+            Utc::now() + chrono::Duration::from_std(lease).map_err(|e| e.to_string())?;
+
+        sqlx::query_as::<_, Row>(&format!(
+            "UPDATE outbox SET attempts = attempts + 1, available_at = $2 \
+             WHERE id IN (SELECT id FROM outbox \
+             WHERE dead_at IS NULL AND available_at <= now() \
+             ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED) \
+             RETURNING {COLUMNS}"
+        ))
+        .bind(limit)
+        .bind(leased_until)
+        .fetch_all(&self.0)
+        .await
+        .map(|rows| rows.into_iter().map(OutboxMessage::from).collect())
+        .map_err(|e| e.to_string())
+    }
+
+    async fn delivered(&self, id: i64) -> Result<(), String> {
+        sqlx::query("DELETE FROM outbox WHERE id = $1")
+            .bind(id)
+            .execute(&self.0)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn failed(
+        &self,
+        id: i64,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), String> {
+        let query = match retry_at {
+            Some(retry_at) => sqlx::query(
+                "UPDATE outbox SET last_error = $2, available_at = $3 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(error)
+            .bind(retry_at),
+            None => {
+                sqlx::query("UPDATE outbox SET last_error = $2, dead_at = now() WHERE id = $1")
+                    .bind(id)
+                    .bind(error)
+            }
+        };
+
+        query
+            .execute(&self.0)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn dead_letters(&self, limit: i64) -> Result<Vec<OutboxMessage>, String> {
+        sqlx::query_as::<_, Row>(&format!(
+            "SELECT {COLUMNS} FROM outbox WHERE dead_at IS NOT NULL ORDER BY id DESC LIMIT $1"
+        ))
+        .bind(limit)
+        .fetch_all(&self.0)
+        .await
+        .map(|rows| rows.into_iter().map(OutboxMessage::from).collect())
+        .map_err(|e| e.to_string())
+    }
+
+    async fn requeue(&self, id: i64) -> Result<bool, String> {
+        sqlx::query(
+            "UPDATE outbox SET attempts = 0, dead_at = NULL, available_at = now() \
+             WHERE id = $1 AND dead_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(&self.0)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .map_err(|e| e.to_string())
+    }
+}
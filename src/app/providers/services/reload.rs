@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
+use rocket::tokio;
+
+use crate::app::providers::config::{self, AuthConfig, SharedConfig};
+
+/// Keys read once at ignite; a reload leaves them as they were.
+const RESTART_KEYS: [&str; 12] = [
+    "address",
+    "port",
+    "udp_port",
+    "databases",
+    "config_reload_interval",
+    "idempotency_ttl",
+    "fetch",
+    "cors",
+    "security_headers",
+    "bff",
+    "outbox",
+    "guest_sweeper",
+];
+
+/// Reloads the configuration on SIGHUP or when the config file changes.
+/// `config_reload_interval` sets how often, in seconds, the file is checked;
+/// 0 leaves only the signal. Only `AuthConfig` is swapped, changes to the
+/// `RESTART_KEYS` are logged and wait for a restart.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Config reload", |rocket| {
+        Box::pin(async move {
+            let shared = match rocket.state::<SharedConfig>() {
+                Some(shared) => shared.clone(),
+                None => return,
+            };
+
+            let interval = rocket
+                .figment()
+                .extract_inner::<u64>("config_reload_interval")
+                .unwrap_or(30);
+
+            tokio::spawn(watch(shared, rocket.figment().clone(), interval));
+        })
+    })
+}
+
+/// Validates the configuration found in `figment` and swaps it in. The
+/// current configuration is kept when validation fails.
+pub fn apply(shared: &SharedConfig, figment: &Figment) -> Result<(), Vec<String>> {
+    let config = AuthConfig::from_figment(figment)?;
+    let previous = shared.swap(config);
+
+    if previous.secret_key != shared.get().secret_key {
+        warn!("CONFIG: secret_key changed; private cookies keep the key loaded at ignite");
+    }
+
+    Ok(())
+}
+
+/// The `RESTART_KEYS` whose value in `figment` differs from the one
+/// loaded at ignite.
+pub fn restart_only_changes(ignited: &Figment, figment: &Figment) -> Vec<&'static str> {
+    RESTART_KEYS
+        .into_iter()
+        .filter(|key| ignited.find_value(key).ok() != figment.find_value(key).ok())
+        .collect()
+}
+
+fn reload(shared: &SharedConfig, ignited: &Figment) {
+    let mut figment = rocket::Config::figment();
+    // Keeps the random key of a debug build
+    if config::lacks_debug_secret_key(&figment) {
+        figment = figment.merge((rocket::Config::SECRET_KEY, shared.get().secret_key.clone()));
+    }
+
+    let pending = restart_only_changes(ignited, &figment);
+    if !pending.is_empty() {
+        warn!(
+            "CONFIG: {} changed; they apply after a restart",
+            pending.join(", ")
+        );
+    }
+
+    match apply(shared, &figment) {
+        Ok(()) => info!("CONFIG: configuration reloaded"),
+        Err(errors) => {
+            for e in errors {
+                error!("CONFIG: reload rejected; {e}");
+            }
+        }
+    }
+}
+
+fn config_path() -> PathBuf {
+    std::env::var("ROCKET_CONFIG")
+        .unwrap_or("Rocket.toml".to_string())
+        .into()
+}
+
+fn modified_at(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+async fn watch(shared: SharedConfig, ignited: Figment, interval: u64) {
+    let path = config_path();
+    let mut modified = modified_at(&path);
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+
+    #[cfg(unix)]
+    let mut hangup =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+    #[cfg(not(unix))]
+    let mut hangup: Option<()> = None;
+
+    loop {
+        tokio::select! {
+            _ = next_hangup(&mut hangup) => {
+                info!("CONFIG: SIGHUP received");
+                reload(&shared, &ignited);
+                modified = modified_at(&path);
+            }
+            _ = ticker.tick(), if interval > 0 => {
+                let current = modified_at(&path);
+                if current != modified {
+                    modified = current;
+                    reload(&shared, &ignited);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn next_hangup(hangup: &mut Option<tokio::signal::unix::Signal>) {
+    match hangup {
+        Some(signal) => {
+            signal.recv().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn next_hangup(_: &mut Option<()>) {
+    std::future::pending().await
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::{Revocation, RevocationStore};
+
+/// Revocation times keyed by user and, for memberships, project.
+type Revoked = HashMap<(i32, Option<i32>), i64>;
+
+/// Revocations pushed to this instance, for builds without a database.
+#[derive(Default)]
+pub struct MemoryRevocations(RwLock<Revoked>);
+
+#[rocket::async_trait]
+impl RevocationStore for MemoryRevocations {
+    async fn revoke(&self, revocation: &Revocation, now: i64, keep: i64) -> Result<(), String> {
+        let mut revoked = self.0.write().unwrap_or_else(|e| e.into_inner());
+
+        revoked.retain(|_, at| *at + keep > now);
+        revoked.insert((revocation.user_id, revocation.project_id), now);
+
+        Ok(())
+    }
+
+    async fn revoked_since(
+        &self,
+        user_id: i32,
+        project_id: Option<i32>,
+        issued_at: i64,
+    ) -> Result<bool, String> {
+        let revoked = self.0.read().unwrap_or_else(|e| e.into_inner());
+        let after = |key| revoked.get(&key).is_some_and(|at| *at >= issued_at);
+
+        Ok(after((user_id, None))
+            || project_id.is_some_and(|project_id| after((user_id, Some(project_id)))))
+    }
+}
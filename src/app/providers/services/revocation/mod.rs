@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use rocket::fairing::AdHoc;
+use rocket::http::Status;
+use serde::Deserialize;
+
+use crate::app::providers::errors::AuthError;
+use crate::app::providers::services::claims::Claims;
+
+pub mod memory;
+#[cfg(feature = "db_sqlx")]
+pub mod postgres;
+
+pub use memory::MemoryRevocations;
+#[cfg(feature = "db_sqlx")]
+pub use postgres::PostgresRevocations;
+
+/// Pushed by the user service when it deactivates a user, or only one of
+/// its memberships when `project_id` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Revocation {
+    pub user_id: i32,
+    pub project_id: Option<i32>,
+}
+
+/// Where revocation times are kept, by user and, for memberships, project.
+#[rocket::async_trait]
+pub trait RevocationStore: Send + Sync {
+    /// Records the revocation at `now` and drops the ones older than `keep`
+    /// seconds, no token issued before them is still valid.
+    async fn revoke(&self, revocation: &Revocation, now: i64, keep: i64) -> Result<(), String>;
+    /// Whether the user, or its membership of `project_id`, was revoked at
+    /// or after `issued_at`.
+    async fn revoked_since(
+        &self,
+        user_id: i32,
+        project_id: Option<i32>,
+        issued_at: i64,
+    ) -> Result<bool, String>;
+}
+
+/// Revocations pushed to the service. Refresh tokens and BFF sessions
+/// issued up to the revocation stop working at once; access tokens already
+/// handed out stay valid until they expire.
+#[derive(Clone)]
+pub struct Revocations(Arc<dyn RevocationStore>);
+
+impl Default for Revocations {
+    fn default() -> Self {
+        Revocations::new(Arc::new(MemoryRevocations::default()))
+    }
+}
+
+impl Revocations {
+    pub fn new(store: Arc<dyn RevocationStore>) -> Self {
+        Revocations(store)
+    }
+
+    pub async fn revoke(&self, revocation: &Revocation, keep: i64) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+
+        self.0.revoke(revocation, now, keep).await
+    }
+
+    /// Fails closed: a store that cannot answer rejects the token.
+    pub async fn is_revoked(
+        &self,
+        user_id: i32,
+        project_id: Option<i32>,
+        issued_at: i64,
+    ) -> Result<bool, AuthError> {
+        self.0
+            .revoked_since(user_id, project_id, issued_at)
+            .await
+            .map_err(|e| {
+                error!("AUTH: revocations could not be read; {e}");
+                AuthError::Http(Status::ServiceUnavailable)
+            })
+    }
+
+    pub async fn revokes(&self, claims: &Claims) -> Result<bool, AuthError> {
+        let project_id = claims.user.project.as_ref().map(|project| project.id);
+
+        self.is_revoked(claims.user.id, project_id, claims.iat)
+            .await
+    }
+}
+
+/// Manages the `revocations` table with `db_sqlx`, or an in memory store
+/// otherwise. Attach after the database fairings.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_ignite("Init revocations", |rocket| async {
+        if rocket.state::<Revocations>().is_some() {
+            return rocket;
+        }
+
+        #[cfg(feature = "db_sqlx")]
+        {
+            use rocket_db_pools::Database;
+
+            if let Some(db) = crate::database::connection::Db::fetch(&rocket) {
+                let store = Arc::new(PostgresRevocations::new(db.0.clone()));
+                return rocket.manage(Revocations::new(store));
+            }
+        }
+
+        warn!("AUTH: no database, revocations only reach the replica they are pushed to");
+        rocket.manage(Revocations::default())
+    })
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use rocket_db_pools::sqlx::{self, PgPool};
+
+use super::{Revocation, RevocationStore};
+
+/// The `revocations` table, shared by every replica.
+pub struct PostgresRevocations(PgPool);
+
+impl PostgresRevocations {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresRevocations(pool)
+    }
+}
+
+fn timestamp(secs: i64) -> Result<DateTime<Utc>, String> {
+    DateTime::from_timestamp(secs, 0).ok_or_else(|| format!("timestamp {secs} out of range"))
+}
+
+#[rocket::async_trait]
+impl RevocationStore for PostgresRevocations {
+    async fn revoke(&self, revocation: &Revocation, now: i64, keep: i64) -> Result<(), String> {
+        let mut tx = self.0.begin().await.map_err(|e| e.to_string())?;
+
+        sqlx::query("DELETE FROM revocations WHERE revoked_at <= $1")
+            .bind(timestamp(now - keep)?)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        // project_id 0 stands for the whole user, the key needs no NULLs
+        sqlx::query(
+            "INSERT INTO revocations (user_id, project_id, revoked_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (user_id, project_id) DO UPDATE SET revoked_at = $3",
+        )
+        .bind(revocation.user_id)
+        .bind(revocation.project_id.unwrap_or(0))
+        .bind(timestamp(now)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    async fn revoked_since(
+        &self,
+        user_id: i32,
+        project_id: Option<i32>,
+        issued_at: i64,
+    ) -> Result<bool, String> {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM revocations WHERE user_id = $1 \
+             AND project_id IN (0, $2) AND revoked_at >= $3)",
+        )
+        .bind(user_id)
+        .bind(project_id.unwrap_or(0))
+        .bind(timestamp(issued_at)?)
+        .fetch_one(&self.0)
+        .await
+        .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+/// A cron schedule read on the wall clock of an IANA timezone.
+///
+/// The cron manager only ticks in UTC, so a zoned schedule ticks every
+/// minute on the seconds of its expression and `is_due` picks the ticks
+/// that match. Wall clock times skipped when clocks go forward run once, on
+/// the first tick after the gap; times repeated when clocks go back only
+/// run on their first occurrence.
+#[derive(Debug, Clone)]
+pub struct TzSchedule {
+    expression: String,
+    schedule: Schedule,
+    carrier_expression: String,
+    carrier: Schedule,
+    timezone: Tz,
+}
+
+impl TzSchedule {
+    pub fn parse(expression: &str, timezone: &str) -> Result<Self, String> {
+        let schedule = Schedule::from_str(expression).map_err(|e| {
+            format!("schedule `{expression}` is not a valid cron expression; {e}")
+        })?;
+        let timezone = Tz::from_str(timezone)
+            .map_err(|_| format!("timezone `{timezone}` is not an IANA timezone"))?;
+
+        let carrier_expression = match timezone {
+            Tz::UTC => expression.to_string(),
+            _ => {
+                let seconds = expression.split_whitespace().next().unwrap_or("0");
+                format!("{seconds} * * * * *")
+            }
+        };
+        let carrier = Schedule::from_str(&carrier_expression)
+            .map_err(|e| format!("schedule `{expression}` has invalid seconds; {e}"))?;
+
+        Ok(TzSchedule {
+            expression: expression.to_string(),
+            schedule,
+            carrier_expression,
+            carrier,
+            timezone,
+        })
+    }
+
+    /// The UTC expression the cron manager ticks on.
+    pub fn carrier(&self) -> &str {
+        &self.carrier_expression
+    }
+
+    /// Whether the carrier tick at `tick` runs the job.
+    pub fn is_due(&self, tick: DateTime<Utc>) -> bool {
+        if self.timezone == Tz::UTC {
+            return true;
+        }
+
+        let tick = round(tick);
+        let previous = match self
+            .carrier
+            .after(&(tick - Duration::seconds(61)))
+            .take_while(|previous| *previous < tick)
+            .last()
+        {
+            Some(previous) => previous,
+            None => return false,
+        };
+
+        let local = self.local(tick);
+        let local_previous = self.local(previous);
+
+        // Clocks went back, the wall clock times from here on already ran
+        if local <= local_previous || self.is_repeated(tick, local) {
+            return false;
+        }
+
+        // Wall clock calendar fields are matched as if they were UTC
+        self.schedule
+            .after(&Utc.from_utc_datetime(&local_previous))
+            .next()
+            .is_some_and(|next| next.naive_utc() <= local)
+    }
+
+    /// The instants the job runs at after `after` and before `before`, at
+    /// most `limit` of them.
+    pub fn ticks(
+        &self,
+        after: DateTime<Utc>,
+        before: DateTime<Utc>,
+        limit: usize,
+    ) -> Vec<DateTime<Utc>> {
+        self.carrier
+            .after(&after)
+            .take_while(|tick| *tick < before)
+            .filter(|tick| self.is_due(*tick))
+            .take(limit)
+            .collect()
+    }
+
+    fn local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        instant.with_timezone(&self.timezone).naive_local()
+    }
+
+    fn is_repeated(&self, instant: DateTime<Utc>, local: NaiveDateTime) -> bool {
+        match self.timezone.from_local_datetime(&local) {
+            LocalResult::Ambiguous(first, _) => first.with_timezone(&Utc) != instant,
+            _ => false,
+        }
+    }
+}
+
+/// Cron expression that only matches `run_at`, to the second.
+pub fn one_shot(run_at: DateTime<Utc>) -> String {
+    format!(
+        "{} {} {} {} {} * {}",
+        run_at.second(),
+        run_at.minute(),
+        run_at.hour(),
+        run_at.day(),
+        run_at.month(),
+        run_at.year()
+    )
+}
+
+/// Ticks fire a few milliseconds late or early.
+fn round(tick: DateTime<Utc>) -> DateTime<Utc> {
+    let tick = tick + Duration::milliseconds(500);
+
+    tick.with_nanosecond(0).unwrap_or(tick)
+}
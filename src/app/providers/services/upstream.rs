@@ -0,0 +1,122 @@
+use rocket::serde::json::serde_json;
+use serde::de::DeserializeOwned;
+
+const EXCERPT_LEN: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpstreamErrorKind {
+    Timeout,
+    Connect,
+    Request,
+    NotConfigured,
+    Status(u16),
+    Decode,
+}
+
+/// Failure while talking to one of the downstream q-api services.
+#[derive(Debug, Clone)]
+pub struct UpstreamError {
+    pub service: &'static str,
+    pub kind: UpstreamErrorKind,
+    pub excerpt: Option<String>,
+}
+
+impl UpstreamError {
+    pub fn new(service: &'static str, kind: UpstreamErrorKind) -> Self {
+        UpstreamError {
+            service,
+            kind,
+            excerpt: None,
+        }
+    }
+
+    pub fn from_reqwest(service: &'static str, error: reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            UpstreamErrorKind::Timeout
+        } else if error.is_connect() {
+            UpstreamErrorKind::Connect
+        } else if error.is_decode() {
+            UpstreamErrorKind::Decode
+        } else {
+            UpstreamErrorKind::Request
+        };
+
+        UpstreamError {
+            service,
+            kind,
+            excerpt: Some(excerpt(error.to_string().as_bytes())),
+        }
+    }
+}
+
+impl std::fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            UpstreamErrorKind::Timeout => write!(f, "{} service timed out", self.service),
+            UpstreamErrorKind::Connect => write!(f, "{} service unreachable", self.service),
+            UpstreamErrorKind::Request => write!(f, "{} request failed", self.service),
+            UpstreamErrorKind::NotConfigured => {
+                write!(f, "{}_url is not configured", self.service)
+            }
+            UpstreamErrorKind::Status(status) => {
+                write!(f, "{} service answered {}", self.service, status)
+            }
+            UpstreamErrorKind::Decode => {
+                write!(f, "{} service sent an unexpected body", self.service)
+            }
+        }?;
+
+        match &self.excerpt {
+            Some(excerpt) if !excerpt.is_empty() => write!(f, "; {excerpt}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+pub fn excerpt(body: &[u8]) -> String {
+    String::from_utf8_lossy(body)
+        .chars()
+        .take(EXCERPT_LEN)
+        .collect()
+}
+
+/// Turns the outcome of `send()` into the response, or an error when the
+/// request failed or the service answered with a non success status.
+pub async fn expect_success(
+    service: &'static str,
+    res: Result<reqwest::Response, reqwest::Error>,
+) -> Result<reqwest::Response, UpstreamError> {
+    let res = res.map_err(|e| UpstreamError::from_reqwest(service, e))?;
+
+    if !res.status().is_success() {
+        let status = res.status().as_u16();
+        let body = res.bytes().await.unwrap_or_default();
+
+        return Err(UpstreamError {
+            service,
+            kind: UpstreamErrorKind::Status(status),
+            excerpt: Some(excerpt(&body)),
+        });
+    }
+
+    Ok(res)
+}
+
+pub async fn read_json<T: DeserializeOwned>(
+    service: &'static str,
+    res: Result<reqwest::Response, reqwest::Error>,
+) -> Result<T, UpstreamError> {
+    let res = expect_success(service, res).await?;
+    let body = res
+        .bytes()
+        .await
+        .map_err(|e| UpstreamError::from_reqwest(service, e))?;
+
+    serde_json::from_slice::<T>(&body).map_err(|e| UpstreamError {
+        service,
+        kind: UpstreamErrorKind::Decode,
+        excerpt: Some(format!("{e}; {}", excerpt(&body))),
+    })
+}
@@ -1,6 +1,15 @@
+use jsonwebtoken::jwk::JwkSet;
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::app::providers::config::SharedConfig;
+use crate::app::providers::errors;
+
 pub fn router() -> rocket::fairing::AdHoc {
     rocket::fairing::AdHoc::on_ignite("Routes", |rocket| async {
-        rocket.mount("/", routes![health])
+        rocket
+            .mount("/", routes![health, jwks])
+            .register("/", errors::catchers())
     })
 }
 
@@ -8,3 +17,12 @@ pub fn router() -> rocket::fairing::AdHoc {
 fn health() -> &'static str {
     "OK"
 }
+
+/// Public keys of the asymmetric `jwt_keys`, for the `q-auth-client` of the
+/// other services. Shared secrets are never published. Not found unless
+/// `serve_jwks` is set.
+#[get("/.well-known/jwks.json")]
+fn jwks(config: &State<SharedConfig>) -> Option<Json<JwkSet>> {
+    let config = config.get();
+    config.serve_jwks.then(|| Json(config.keys.jwks()))
+}
@@ -3,16 +3,27 @@ use crate::app::providers::services::cron::CronManager;
 
 #[cfg(any(feature = "db_diesel", feature = "db_sqlx"))]
 use crate::database::connection;
-#[cfg(any(feature = "db_diesel", feature = "db_sqlx"))]
+#[cfg(any(
+    feature = "db_diesel",
+    feature = "db_sqlx",
+    feature = "fetch",
+    feature = "cron"
+))]
 use rocket::fairing::AdHoc;
 
 #[cfg(feature = "db_sqlx")]
 use rocket_db_pools::Database;
 
+#[cfg(feature = "fetch")]
+use crate::app::providers::services::clients;
 #[cfg(feature = "fetch")]
 use crate::app::providers::services::fetch::Fetch;
 
+use crate::app::providers::config::AuthConfig;
 use crate::app::providers::cors;
+use crate::app::providers::security::SecurityHeaders;
+use crate::app::providers::services::guests::{self, sweeper};
+use crate::app::providers::services::{audit, outbox, reload, revocation};
 
 use super::modules::routing as modules_routing;
 use super::routing as service_routing;
@@ -20,7 +31,9 @@ use super::routing as service_routing;
 #[launch]
 pub async fn rocket() -> _ {
     #[allow(unused_mut)]
-    let mut rocket_build = rocket::build();
+    let mut rocket_build = rocket::build()
+        .attach(AuthConfig::fairing())
+        .attach(reload::fairing());
 
     #[cfg(feature = "db_diesel")]
     {
@@ -44,17 +57,27 @@ pub async fn rocket() -> _ {
 
     #[cfg(feature = "fetch")]
     {
-        rocket_build = rocket_build.manage(Fetch::new());
+        rocket_build = rocket_build
+            .attach(AdHoc::try_on_ignite("Init Fetch", Fetch::init))
+            .attach(clients::fairing())
+            .attach(outbox::dispatcher::fairing());
     }
 
     #[cfg(feature = "cron")]
     {
-        rocket_build =
-            rocket_build.attach(AdHoc::on_ignite("Init CronManager", CronManager::init));
+        rocket_build = rocket_build
+            .attach(AdHoc::on_ignite("Init CronManager", CronManager::init))
+            .attach(sweeper::schedule());
     }
 
     rocket_build
+        .attach(audit::fairing())
+        .attach(outbox::fairing())
+        .attach(guests::fairing())
+        .attach(revocation::fairing())
+        .attach(sweeper::fairing())
         .attach(cors::Cors)
+        .attach(SecurityHeaders)
         .attach(service_routing::router())
         .attach(modules_routing::router())
 }
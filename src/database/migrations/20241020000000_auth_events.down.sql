@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS auth_events_append_only ON auth_events;
+DROP FUNCTION IF EXISTS auth_events_append_only();
+DROP TABLE IF EXISTS auth_events;
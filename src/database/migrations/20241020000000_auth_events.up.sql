@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS auth_events (
+    id          BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    kind        VARCHAR(32) NOT NULL,
+    -- "success" or the error code sent to the client
+    outcome     VARCHAR(64) NOT NULL,
+    actor_id    INTEGER,
+    subject_id  INTEGER,
+    ip          VARCHAR(45),
+    user_agent  TEXT,
+    client      VARCHAR(64)
+);
+
+CREATE INDEX auth_events_subject_idx ON auth_events (subject_id, id DESC);
+CREATE INDEX auth_events_actor_idx ON auth_events (actor_id, id DESC);
+CREATE INDEX auth_events_kind_idx ON auth_events (kind, id DESC);
+CREATE INDEX auth_events_occurred_at_idx ON auth_events (occurred_at);
+
+CREATE FUNCTION auth_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'auth_events is append only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auth_events_append_only
+    BEFORE UPDATE OR DELETE ON auth_events
+    FOR EACH ROW EXECUTE FUNCTION auth_events_append_only();
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS outbox (
+    id           BIGSERIAL PRIMARY KEY,
+    effect       JSONB NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- next attempt, or the end of the lease of the dispatcher holding it
+    available_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error   TEXT,
+    -- set when it ran out of attempts; dead letters wait for a requeue
+    dead_at      TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_due_idx ON outbox (available_at) WHERE dead_at IS NULL;
+CREATE INDEX outbox_dead_idx ON outbox (id DESC) WHERE dead_at IS NOT NULL;
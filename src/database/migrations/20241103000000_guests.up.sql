@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS guests (
+    user_id    INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- NULL for projects without a guest lifetime
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX guests_expires_at_idx ON guests (expires_at) WHERE expires_at IS NOT NULL;
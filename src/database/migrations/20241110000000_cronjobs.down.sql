@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS cronjobs;
+DROP TABLE IF EXISTS escalonjobs;
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS escalonjobs (
+    id       UUID PRIMARY KEY,
+    status   VARCHAR NOT NULL,
+    schedule VARCHAR NOT NULL,
+    since    TIMESTAMP,
+    until    TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS cronjobs (
+    id      SERIAL PRIMARY KEY,
+    -- identity of the node that schedules the job
+    owner   VARCHAR NOT NULL,
+    service VARCHAR NOT NULL,
+    route   VARCHAR NOT NULL,
+    job_id  UUID NOT NULL UNIQUE REFERENCES escalonjobs (id)
+);
+
+CREATE INDEX cronjobs_owner_idx ON cronjobs (owner);
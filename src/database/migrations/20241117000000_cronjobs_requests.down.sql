@@ -0,0 +1,4 @@
+ALTER TABLE cronjobs
+    DROP COLUMN IF EXISTS method,
+    DROP COLUMN IF EXISTS body,
+    DROP COLUMN IF EXISTS timeout;
@@ -0,0 +1,5 @@
+ALTER TABLE cronjobs
+    ADD COLUMN method  VARCHAR NOT NULL DEFAULT 'GET',
+    ADD COLUMN body    JSONB,
+    -- seconds, the fetch timeout of the service when NULL
+    ADD COLUMN timeout INTEGER;
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS cron_runs;
+
+ALTER TABLE cronjobs
+    DROP COLUMN IF EXISTS retry_attempts,
+    DROP COLUMN IF EXISTS retry_backoff,
+    DROP COLUMN IF EXISTS misfire,
+    DROP COLUMN IF EXISTS created_at;
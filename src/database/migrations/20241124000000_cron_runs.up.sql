@@ -0,0 +1,25 @@
+ALTER TABLE cronjobs
+    ADD COLUMN retry_attempts INTEGER NOT NULL DEFAULT 0,
+    -- seconds before the first retry, doubled for each next one
+    ADD COLUMN retry_backoff  INTEGER NOT NULL DEFAULT 5,
+    -- fire_once, skip or catch_up
+    ADD COLUMN misfire        VARCHAR NOT NULL DEFAULT 'skip',
+    ADD COLUMN created_at     TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE TABLE IF NOT EXISTS cron_runs (
+    id          BIGSERIAL PRIMARY KEY,
+    cronjob_id  INTEGER NOT NULL REFERENCES cronjobs (id) ON DELETE CASCADE,
+    -- schedule or misfire
+    cause       VARCHAR NOT NULL,
+    attempt     INTEGER NOT NULL,
+    -- identity of the node that ran it
+    node        VARCHAR NOT NULL,
+    started_at  TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    http_status INTEGER,
+    success     BOOLEAN NOT NULL,
+    excerpt     TEXT
+);
+
+CREATE INDEX cron_runs_cronjob_idx ON cron_runs (cronjob_id, started_at DESC);
@@ -0,0 +1,5 @@
+ALTER TABLE escalonjobs
+    DROP COLUMN IF EXISTS run_at,
+    DROP COLUMN IF EXISTS timezone,
+    ALTER COLUMN since TYPE TIMESTAMP USING since AT TIME ZONE 'UTC',
+    ALTER COLUMN until TYPE TIMESTAMP USING until AT TIME ZONE 'UTC';
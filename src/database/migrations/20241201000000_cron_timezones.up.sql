@@ -0,0 +1,8 @@
+-- since and until were naive UTC
+ALTER TABLE escalonjobs
+    ALTER COLUMN since TYPE TIMESTAMPTZ USING since AT TIME ZONE 'UTC',
+    ALTER COLUMN until TYPE TIMESTAMPTZ USING until AT TIME ZONE 'UTC',
+    -- IANA name the schedule is read in
+    ADD COLUMN timezone VARCHAR NOT NULL DEFAULT 'UTC',
+    -- one-shot jobs, with an empty schedule
+    ADD COLUMN run_at   TIMESTAMPTZ;
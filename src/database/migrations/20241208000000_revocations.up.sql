@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS revocations (
+    user_id    INTEGER NOT NULL,
+    -- 0 when the whole user is revoked, not only one membership
+    project_id INTEGER NOT NULL DEFAULT 0,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, project_id)
+);
+
+CREATE INDEX revocations_revoked_at_idx ON revocations (revoked_at);